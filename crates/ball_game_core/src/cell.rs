@@ -0,0 +1,24 @@
+/// Content of a grid cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Cell {
+    #[default]
+    Empty,
+    /// Stops balls, and is destroyed once its lives run out.
+    Block { lives: usize },
+    /// Adds a ball from the next shot on.
+    Ball,
+    /// Worth [`Scoring::per_coin`](crate::Scoring::per_coin) points.
+    Coin,
+}
+
+impl Cell {
+    pub fn is_block(self) -> bool {
+        matches!(self, Cell::Block { .. })
+    }
+
+    /// Whether the first ball passing through collects it.
+    pub fn is_pickup(self) -> bool {
+        matches!(self, Cell::Ball | Cell::Coin)
+    }
+}
@@ -0,0 +1,123 @@
+use rand::{
+    seq::{index, SliceRandom},
+    Rng,
+};
+
+use crate::Cell;
+
+/// How new rows get harder as the game goes on. Lives of a block are
+/// `lives_per_round * round + lives_per_ball * ball_count`, rounded up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Difficulty {
+    /// Chance of each cell in the first row holding a block.
+    pub density: f64,
+    /// Added to `density` every round, up to `max_density`.
+    pub density_per_round: f64,
+    pub max_density: f64,
+    /// Cells of each row kept free so there is always a way up.
+    pub min_empty: usize,
+    pub lives_per_round: f64,
+    pub lives_per_ball: f64,
+    pub min_lives: usize,
+    /// Chance of a block getting twice the lives.
+    pub double_chance: f64,
+    /// Chance of a row getting a +1 ball pickup in one of its free cells.
+    pub ball_chance: f64,
+    /// Chance of a row getting a coin in one of its free cells.
+    pub coin_chance: f64,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self {
+            density: 0.4,
+            density_per_round: 0.005,
+            max_density: 0.7,
+            min_empty: 1,
+            lives_per_round: 0.75,
+            lives_per_ball: 0.25,
+            min_lives: 1,
+            double_chance: 0.1,
+            ball_chance: 1.0,
+            coin_chance: 0.2,
+        }
+    }
+}
+
+impl Difficulty {
+    pub fn density_at(&self, round: usize) -> f64 {
+        (self.density + self.density_per_round * round as f64)
+            .min(self.max_density)
+            .clamp(0.0, 1.0)
+    }
+
+    pub fn lives_at(&self, round: usize, ball_count: usize) -> usize {
+        let lives = self.lives_per_round * round as f64 + self.lives_per_ball * ball_count as f64;
+        (lives.ceil() as usize).max(self.min_lives)
+    }
+
+    /// Cells of the row added in `round`.
+    pub fn row(
+        &self,
+        columns: usize,
+        round: usize,
+        ball_count: usize,
+        rng: &mut impl Rng,
+    ) -> Vec<Cell> {
+        let density = self.density_at(round);
+        let lives = self.lives_at(round, ball_count);
+        let mut row = (0..columns)
+            .map(|_| {
+                if !rng.gen_bool(density) {
+                    0
+                } else if rng.gen_bool(self.double_chance.clamp(0.0, 1.0)) {
+                    lives * 2
+                } else {
+                    lives
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let empty = row.iter().filter(|&&lives| lives == 0).count();
+        let min_empty = self.min_empty.min(columns);
+        if empty < min_empty {
+            let filled = row
+                .iter()
+                .enumerate()
+                .filter(|(_, &lives)| lives > 0)
+                .map(|(column, _)| column)
+                .collect::<Vec<_>>();
+            for picked in index::sample(rng, filled.len(), min_empty - empty) {
+                row[filled[picked]] = 0;
+            }
+        }
+
+        let mut row = row
+            .into_iter()
+            .map(|lives| match lives {
+                0 => Cell::Empty,
+                lives => Cell::Block { lives },
+            })
+            .collect::<Vec<_>>();
+        for (pickup, chance) in [
+            (Cell::Ball, self.ball_chance),
+            (Cell::Coin, self.coin_chance),
+        ] {
+            if !rng.gen_bool(chance.clamp(0.0, 1.0)) {
+                continue;
+            }
+            let free = (0..columns)
+                .filter(|&column| row[column] == Cell::Empty)
+                .collect::<Vec<_>>();
+            if let Some(&column) = free.choose(rng) {
+                row[column] = pickup;
+            }
+        }
+        row
+    }
+}
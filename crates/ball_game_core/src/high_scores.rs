@@ -0,0 +1,44 @@
+/// Final score of a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HighScore {
+    pub points: usize,
+    pub round: usize,
+}
+
+/// Best scores, highest first. Ties keep the older score ahead.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HighScores {
+    entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    pub const CAPACITY: usize = 10;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> &[HighScore] {
+        &self.entries
+    }
+
+    pub fn best(&self) -> Option<HighScore> {
+        self.entries.first().copied()
+    }
+
+    /// Adds `score` if it makes the table and returns its rank, 0 being the
+    /// best.
+    pub fn insert(&mut self, score: HighScore) -> Option<usize> {
+        let rank = self
+            .entries
+            .partition_point(|entry| (entry.points, entry.round) >= (score.points, score.round));
+        if rank >= Self::CAPACITY {
+            return None;
+        }
+        self.entries.insert(rank, score);
+        self.entries.truncate(Self::CAPACITY);
+        Some(rank)
+    }
+}
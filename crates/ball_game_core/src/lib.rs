@@ -0,0 +1,444 @@
+mod cell;
+mod difficulty;
+mod high_scores;
+#[cfg(test)]
+mod tests;
+
+pub use cell::Cell;
+pub use difficulty::Difficulty;
+pub use high_scores::{HighScore, HighScores};
+
+use ball_simulation::{
+    CollisionData, CollisionType, Emitter, Event, EventType, Sensor, SensorData, SensorEventType,
+    SimulationState, StallDetection, WallType,
+};
+use nalgebra::Vector2;
+use ndarray::{Array2, ArrayView1, Axis};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{error::Error, fmt};
+
+/// Shape of the block in column `x` and row `y` of a grid, rows counting up
+/// from the bottom.
+pub fn get_block(columns: usize, rows: usize, x: usize, y: usize) -> ball_simulation::Block {
+    ball_simulation::Block::grid_cell(columns, rows, x, y, 0.04)
+}
+
+/// Area of the pickup in column `x` and row `y`, smaller than a block.
+pub fn get_pickup(columns: usize, rows: usize, x: usize, y: usize) -> Sensor {
+    let area = ball_simulation::Block::grid_cell(columns, rows, x, y, 0.025);
+    Sensor::new(area.max_y, area.min_y, area.min_x, area.max_x)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LaunchSettings {
+    pub speed: f64,
+    /// Smallest angle from the floor, in radians.
+    pub min_angle: f64,
+}
+
+impl Default for LaunchSettings {
+    fn default() -> Self {
+        Self {
+            speed: 2.0,
+            min_angle: 0.15,
+        }
+    }
+}
+
+/// Points given for each hit, each destroyed block and each collected coin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Scoring {
+    pub per_hit: usize,
+    pub per_block: usize,
+    pub per_coin: usize,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Self {
+            per_hit: 1,
+            per_block: 10,
+            per_coin: 5,
+        }
+    }
+}
+
+/// Seed shared by everyone playing on a given day, counted in days since
+/// 1970-01-01 (UTC). It is the date written as `yyyymmdd`, so it can be read
+/// back and entered by hand like any other seed.
+pub fn daily_seed(days_since_epoch: u64) -> u64 {
+    // Civil date from a day count, with years starting in March so the leap
+    // day comes last
+    let days = days_since_epoch + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    year * 10_000 + month * 100 + day
+}
+
+/// Everything kept between turns.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Board {
+    /// Indexed by row then column.
+    pub cells: Array2<Cell>,
+    pub ball_count: usize,
+    pub launcher_position: f64,
+    pub launch: LaunchSettings,
+    pub scoring: Scoring,
+    /// Rows added so far.
+    pub round: usize,
+    pub points: usize,
+    /// Decides every row, the same seed always gives the same rows.
+    pub seed: u64,
+    /// Balancing data, not part of a saved board.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub difficulty: Difficulty,
+}
+
+/// Blocks in the bottom row had nowhere left to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameOver;
+
+impl fmt::Display for GameOver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "blocks reached the launcher row")
+    }
+}
+
+impl Error for GameOver {}
+
+/// What a finished shot changed on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ShotResult {
+    pub hits: usize,
+    pub blocks_destroyed: usize,
+    pub balls_gained: usize,
+    pub coins: usize,
+    pub points: usize,
+}
+
+impl Board {
+    pub fn new(columns: usize, rows: usize, seed: u64) -> Self {
+        Self {
+            cells: Array2::default((rows, columns)),
+            ball_count: 1,
+            launcher_position: 0.5,
+            launch: LaunchSettings::default(),
+            scoring: Scoring::default(),
+            round: 0,
+            points: 0,
+            seed,
+            difficulty: Difficulty::default(),
+        }
+    }
+
+    pub fn score(&self) -> HighScore {
+        HighScore {
+            points: self.points,
+            round: self.round,
+        }
+    }
+
+    /// Whether blocks are in the bottom row, right above the launcher.
+    pub fn reached_launcher(&self) -> bool {
+        self.cells.row(0).iter().any(|cell| cell.is_block())
+    }
+
+    pub fn columns(&self) -> usize {
+        self.cells.ncols()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.cells.nrows()
+    }
+
+    pub fn block(&self, x: usize, y: usize) -> ball_simulation::Block {
+        get_block(self.columns(), self.rows(), x, y)
+    }
+
+    pub fn pickup(&self, x: usize, y: usize) -> Sensor {
+        get_pickup(self.columns(), self.rows(), x, y)
+    }
+
+    /// Shape and lives of every block.
+    pub fn blocks(&self) -> Vec<(ball_simulation::Block, usize)> {
+        self.cells
+            .indexed_iter()
+            .filter_map(|((y, x), &cell)| match cell {
+                Cell::Block { lives } => Some((self.block(x, y), lives)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Area and kind of every pickup.
+    pub fn pickups(&self) -> Vec<(Sensor, Cell)> {
+        self.cells
+            .indexed_iter()
+            .filter(|(_, cell)| cell.is_pickup())
+            .map(|((y, x), &cell)| (self.pickup(x, y), cell))
+            .collect()
+    }
+
+    /// Adds a random row at the top and moves every other row down by one,
+    /// pickups left in the bottom row being lost. Fails without changing the
+    /// board when that would push blocks into the launcher.
+    pub fn advance_row(&mut self) -> Result<(), GameOver> {
+        if self.reached_launcher() {
+            return Err(GameOver);
+        }
+
+        let row = self.difficulty.row(
+            self.columns(),
+            self.round + 1,
+            self.ball_count,
+            &mut self.row_rng(),
+        );
+
+        self.cells.push_row(ArrayView1::from(&row)).unwrap();
+        self.cells.remove_index(Axis(0), 0);
+        self.round += 1;
+        Ok(())
+    }
+
+    // Fresh generator for each round, so nothing besides the seed needs saving
+    fn row_rng(&self) -> StdRng {
+        StdRng::seed_from_u64(self.seed ^ (self.round as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    }
+
+    /// Starts a shot towards `direction`, clamped to the launch settings.
+    pub fn aim(&self, direction: Vector2<f64>) -> Turn {
+        let mut block_cells = vec![];
+        let mut blocks = vec![];
+        let mut pickup_cells = vec![];
+        let mut sensors = vec![];
+        for ((y, x), &cell) in self.cells.indexed_iter() {
+            match cell {
+                Cell::Empty => {}
+                Cell::Block { lives } => {
+                    block_cells.push((Vector2::new(x, y), lives));
+                    blocks.push(self.block(x, y));
+                }
+                Cell::Ball | Cell::Coin => {
+                    pickup_cells.push((Vector2::new(x, y), cell));
+                    sensors.push(self.pickup(x, y));
+                }
+            }
+        }
+
+        Turn {
+            state: SimulationState {
+                time: 0.0,
+                space_width: 1.0,
+                space_height: 1.0,
+                balls: vec![],
+                blocks: blocks.into(),
+                sensors,
+                // Keeps flat shots from bouncing between the side walls for ages
+                stall_detection: Some(StallDetection::default()),
+                ..Default::default()
+            },
+            emitter: Emitter {
+                position: Vector2::new(self.launcher_position, 0.0),
+                direction,
+                speed: self.launch.speed,
+                min_angle: self.launch.min_angle,
+                ..Default::default()
+            },
+            blocks: block_cells,
+            pickups: pickup_cells,
+            scoring: self.scoring,
+            balls_left: self.ball_count,
+            result: ShotResult::default(),
+            landing: None,
+            next: None,
+        }
+    }
+
+    /// Writes the outcome of `turn` so far to the board. Balls still in flight
+    /// are lost, so resolving an unfinished turn stops the shot where it is.
+    pub fn resolve_shot(&mut self, turn: Turn) -> ShotResult {
+        self.cells.fill(Cell::Empty);
+        for &(position, lives) in &turn.blocks {
+            self.cells[(position.y, position.x)] = Cell::Block { lives };
+        }
+        for &(position, cell) in &turn.pickups {
+            self.cells[(position.y, position.x)] = cell;
+        }
+        self.ball_count += turn.result.balls_gained;
+        self.points += turn.result.points;
+        if let Some(landing) = turn.landing {
+            self.launcher_position = landing;
+        }
+        turn.result
+    }
+}
+
+/// What an event of a turn meant for the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnEvent {
+    /// A new ball was added at the end of the simulation's balls.
+    Launched,
+    /// The ball reached the floor and was removed.
+    Returned {
+        ball: usize,
+    },
+    /// The block lost a life, and was removed if none are left.
+    Hit {
+        block: usize,
+        lives: usize,
+    },
+    /// A ball went through the pickup, which was removed.
+    Collected {
+        pickup: usize,
+        cell: Cell,
+    },
+    Other,
+}
+
+/// A shot being played, one event at a time.
+#[derive(Debug, Clone)]
+pub struct Turn {
+    pub state: SimulationState,
+    emitter: Emitter,
+    // Grid cell and lives of each block of `state`
+    blocks: Vec<(Vector2<usize>, usize)>,
+    // Grid cell and content of each sensor of `state`
+    pickups: Vec<(Vector2<usize>, Cell)>,
+    scoring: Scoring,
+    balls_left: usize,
+    result: ShotResult,
+    landing: Option<f64>,
+    next: Option<(SimulationState, Event<EventType>)>,
+}
+
+impl Turn {
+    pub fn emitter(&self) -> &Emitter {
+        &self.emitter
+    }
+
+    pub fn balls_left(&self) -> usize {
+        self.balls_left
+    }
+
+    pub fn result(&self) -> ShotResult {
+        self.result
+    }
+
+    /// Where the first ball to come back hit the floor, which is where the
+    /// next shot is launched from.
+    pub fn landing(&self) -> Option<f64> {
+        self.landing
+    }
+
+    /// Grid cell and lives of each block still standing, in the order of the
+    /// simulation's blocks.
+    pub fn blocks(&self) -> &[(Vector2<usize>, usize)] {
+        &self.blocks
+    }
+
+    /// Grid cell and content of each pickup left, in the order of the
+    /// simulation's sensors.
+    pub fn pickups(&self) -> &[(Vector2<usize>, Cell)] {
+        &self.pickups
+    }
+
+    /// Simulation time of the next event, or `None` when the shot is over.
+    pub fn next_time(&mut self) -> Option<f64> {
+        if self.next.is_none() {
+            let spawn_events = (self.balls_left > 0)
+                .then(|| self.emitter.next_launch(self.state.time))
+                .into_iter()
+                .collect::<Vec<_>>();
+            self.next = self.state.clone().next(&spawn_events);
+        }
+        self.next.as_ref().map(|(state, _)| state.time)
+    }
+
+    pub fn is_finished(&mut self) -> bool {
+        self.next_time().is_none()
+    }
+
+    /// Moves to the next event and applies the game rules to it.
+    pub fn step(&mut self) -> Option<(Event<EventType>, TurnEvent)> {
+        self.next_time()?;
+        let (state, event) = self.next.take().unwrap();
+        self.state = state;
+
+        let turn_event = match event.data {
+            EventType::Custom => {
+                self.state.balls.push(self.emitter.ball());
+                self.balls_left -= 1;
+                TurnEvent::Launched
+            }
+            EventType::Collision(CollisionData {
+                ball,
+                against: CollisionType::Wall(WallType::YNegative),
+            }) => {
+                let returned = self.state.balls.remove(ball);
+                self.landing.get_or_insert(returned.position.x);
+                TurnEvent::Returned { ball }
+            }
+            EventType::Collision(CollisionData {
+                against: CollisionType::Block { index, .. },
+                ..
+            }) => {
+                let lives = &mut self.blocks[index].1;
+                *lives -= 1;
+                let lives = *lives;
+                self.result.hits += 1;
+                self.result.points += self.scoring.per_hit;
+                if lives == 0 {
+                    self.state.blocks.remove(index);
+                    self.blocks.remove(index);
+                    self.result.blocks_destroyed += 1;
+                    self.result.points += self.scoring.per_block;
+                }
+                TurnEvent::Hit {
+                    block: index,
+                    lives,
+                }
+            }
+            EventType::Sensor(SensorData {
+                sensor,
+                kind: SensorEventType::Enter,
+                ..
+            }) => {
+                self.state.sensors.remove(sensor);
+                let (_, cell) = self.pickups.remove(sensor);
+                match cell {
+                    Cell::Ball => self.result.balls_gained += 1,
+                    Cell::Coin => {
+                        self.result.coins += 1;
+                        self.result.points += self.scoring.per_coin;
+                    }
+                    Cell::Empty | Cell::Block { .. } => {}
+                }
+                TurnEvent::Collected {
+                    pickup: sensor,
+                    cell,
+                }
+            }
+            _ => TurnEvent::Other,
+        };
+
+        Some((event, turn_event))
+    }
+}
@@ -0,0 +1,322 @@
+use nalgebra::Vector2;
+use ndarray::s;
+
+use crate::{
+    daily_seed, Board, Cell, Difficulty, GameOver, HighScore, HighScores, ShotResult, TurnEvent,
+};
+
+fn total_lives(board: &Board) -> usize {
+    board.blocks().iter().map(|&(_, lives)| lives).sum()
+}
+
+fn count(board: &Board, cell: Cell) -> usize {
+    board.cells.iter().filter(|&&other| other == cell).count()
+}
+
+#[test]
+fn new_rows_move_down() {
+    let mut board = Board::new(6, 5, 1);
+
+    board.advance_row().unwrap();
+    let top = board.cells.row(4).to_owned();
+    assert!(board
+        .cells
+        .slice(s![..4, ..])
+        .iter()
+        .all(|&cell| cell == Cell::Empty));
+
+    board.advance_row().unwrap();
+    assert_eq!(board.cells.row(3), top);
+    assert_eq!(board.cells.shape(), &[5, 6]);
+}
+
+#[test]
+fn straight_shot_destroys_block_above() {
+    let mut board = Board::new(3, 3, 0);
+    board.cells[(1, 1)] = Cell::Block { lives: 1 };
+    board.cells[(2, 0)] = Cell::Block { lives: 2 };
+
+    let mut turn = board.aim(Vector2::new(0.0, 1.0));
+    let mut events = vec![];
+    while let Some((_, event)) = turn.step() {
+        events.push(event);
+    }
+    assert_eq!(
+        events,
+        [
+            TurnEvent::Launched,
+            TurnEvent::Hit { block: 0, lives: 0 },
+            TurnEvent::Returned { ball: 0 },
+        ]
+    );
+
+    let result = board.resolve_shot(turn);
+    assert_eq!(
+        result,
+        ShotResult {
+            hits: 1,
+            blocks_destroyed: 1,
+            balls_gained: 0,
+            coins: 0,
+            points: 11,
+        }
+    );
+    assert_eq!(board.ball_count, 1);
+    assert_eq!(board.points, 11);
+    assert_eq!(board.launcher_position, 0.5);
+    assert_eq!(board.cells[(1, 1)], Cell::Empty);
+    assert_eq!(board.cells[(2, 0)], Cell::Block { lives: 2 });
+}
+
+#[test]
+fn balls_collect_pickups_on_the_way() {
+    let mut board = Board::new(3, 4, 0);
+    board.cells[(0, 1)] = Cell::Ball;
+    board.cells[(1, 1)] = Cell::Coin;
+    board.cells[(2, 1)] = Cell::Block { lives: 2 };
+    board.cells[(3, 0)] = Cell::Ball;
+    board.ball_count = 2;
+
+    let mut turn = board.aim(Vector2::new(0.0, 1.0));
+    let mut events = vec![];
+    while let Some((_, event)) = turn.step() {
+        if event != TurnEvent::Other {
+            events.push(event);
+        }
+    }
+    // The second ball follows the first, finding the pickups already gone
+    assert_eq!(
+        events,
+        [
+            TurnEvent::Launched,
+            TurnEvent::Collected {
+                pickup: 0,
+                cell: Cell::Ball,
+            },
+            TurnEvent::Launched,
+            TurnEvent::Collected {
+                pickup: 0,
+                cell: Cell::Coin,
+            },
+            TurnEvent::Hit { block: 0, lives: 1 },
+            TurnEvent::Hit { block: 0, lives: 0 },
+            TurnEvent::Returned { ball: 0 },
+            TurnEvent::Returned { ball: 0 },
+        ]
+    );
+
+    let result = board.resolve_shot(turn);
+    assert_eq!(
+        result,
+        ShotResult {
+            hits: 2,
+            blocks_destroyed: 1,
+            balls_gained: 1,
+            coins: 1,
+            points: 17,
+        }
+    );
+    assert_eq!(board.ball_count, 3);
+    assert_eq!(count(&board, Cell::Ball), 1);
+    assert_eq!(board.cells[(3, 0)], Cell::Ball);
+    assert_eq!(count(&board, Cell::Coin), 0);
+}
+
+#[test]
+fn stopped_shot_keeps_hits_so_far() {
+    let mut board = Board::new(3, 3, 0);
+    board.cells[(1, 1)] = Cell::Block { lives: 3 };
+
+    let mut turn = board.aim(Vector2::new(0.0, 1.0));
+    while !matches!(turn.step(), Some((_, TurnEvent::Hit { .. }))) {}
+
+    let result = board.resolve_shot(turn);
+    assert_eq!(result.hits, 1);
+    assert_eq!(result.balls_gained, 0);
+    assert_eq!(board.ball_count, 1);
+    assert_eq!(board.points, board.scoring.per_hit);
+    assert_eq!(board.cells[(1, 1)], Cell::Block { lives: 2 });
+}
+
+#[test]
+fn flat_aim_is_clamped() {
+    let board = Board::new(3, 3, 0);
+    let turn = board.aim(Vector2::new(1.0, 0.0));
+    let direction = turn.emitter().launch_direction();
+    assert!(direction.y >= board.launch.min_angle.sin() - 1e-12);
+}
+
+#[test]
+fn multi_turn_game() {
+    let mut board = Board::new(7, 8, 7);
+    let mut total_hits = 0;
+
+    for turn_number in 0..6 {
+        board.advance_row().unwrap();
+        let lives_before = total_lives(&board);
+        let pickups_before = count(&board, Cell::Ball);
+        let balls_before = board.ball_count;
+        let points_before = board.points;
+
+        let direction = Vector2::new(turn_number as f64 - 2.5, 4.0);
+        let mut turn = board.aim(direction);
+        let mut launched = 0;
+        let mut hits = 0;
+        while let Some((_, event)) = turn.step() {
+            match event {
+                TurnEvent::Launched => launched += 1,
+                TurnEvent::Hit { .. } => hits += 1,
+                _ => {}
+            }
+        }
+        assert_eq!(launched, balls_before);
+        assert!(hits <= lives_before);
+
+        let result = board.resolve_shot(turn);
+        assert_eq!(board.ball_count, balls_before + result.balls_gained);
+        assert_eq!(
+            count(&board, Cell::Ball),
+            pickups_before - result.balls_gained
+        );
+        assert_eq!(total_lives(&board), lives_before - result.hits);
+        assert_eq!(
+            board.points,
+            points_before + result.hits + 10 * result.blocks_destroyed + 5 * result.coins
+        );
+        total_hits += result.hits;
+    }
+    assert!(total_hits > 0);
+    assert!(board.ball_count > 1);
+}
+
+#[test]
+fn blocks_reaching_the_launcher_end_the_game() {
+    let mut board = Board::new(4, 3, 3);
+    board.cells[(2, 1)] = Cell::Block { lives: 5 };
+    board.cells[(1, 2)] = Cell::Coin;
+
+    board.advance_row().unwrap();
+    // Pickups in the bottom row are simply lost
+    assert!(!board.reached_launcher());
+    board.advance_row().unwrap();
+    assert_eq!(count(&board, Cell::Coin), 0);
+    assert!(board.reached_launcher());
+    let cells = board.cells.clone();
+    assert_eq!(board.advance_row(), Err(GameOver));
+    assert_eq!(board.cells, cells);
+    assert_eq!(board.score().round, 2);
+}
+
+#[test]
+fn high_score_table() {
+    let mut scores = HighScores::new();
+    let score = |points| HighScore { points, round: 1 };
+
+    assert_eq!(scores.insert(score(10)), Some(0));
+    assert_eq!(scores.insert(score(30)), Some(0));
+    assert_eq!(scores.insert(score(10)), Some(2));
+    assert_eq!(scores.insert(score(20)), Some(1));
+    assert_eq!(scores.best(), Some(score(30)));
+
+    for _ in 0..HighScores::CAPACITY {
+        scores.insert(score(50));
+    }
+    assert_eq!(scores.entries().len(), HighScores::CAPACITY);
+    assert_eq!(scores.insert(score(5)), None);
+    assert_eq!(scores.insert(score(60)), Some(0));
+}
+
+#[test]
+fn rows_follow_the_seed() {
+    let rows = |seed| {
+        let mut board = Board::new(12, 4, seed);
+        for _ in 0..3 {
+            board.advance_row().unwrap();
+        }
+        board.cells
+    };
+    assert_eq!(rows(5), rows(5));
+    assert_ne!(rows(5), rows(6));
+
+    // A board copied halfway keeps getting the same rows
+    let mut board = Board::new(12, 4, 9);
+    board.advance_row().unwrap();
+    let mut copy = board.clone();
+    board.advance_row().unwrap();
+    copy.advance_row().unwrap();
+    assert_eq!(board.cells, copy.cells);
+    assert_ne!(board.cells.row(3), board.cells.row(2));
+}
+
+#[test]
+fn launcher_follows_first_landing() {
+    let mut board = Board::new(5, 5, 0);
+    board.ball_count = 3;
+
+    let mut turn = board.aim(Vector2::new(1.0, 1.5));
+    let mut landings = vec![];
+    while let Some((_, event)) = turn.step() {
+        if let TurnEvent::Returned { .. } = event {
+            landings.push(turn.landing().unwrap());
+        }
+    }
+    assert_eq!(landings.len(), 3);
+    assert!(landings.iter().all(|&landing| landing == landings[0]));
+    // Off the right wall once, so the ball lands to the left of where it left
+    assert!(landings[0] < 0.5);
+
+    board.resolve_shot(turn);
+    assert_eq!(board.launcher_position, landings[0]);
+    let turn = board.aim(Vector2::new(0.0, 1.0));
+    assert_eq!(turn.emitter().position.x, landings[0]);
+}
+
+#[test]
+fn rows_get_harder() {
+    let difficulty = Difficulty {
+        double_chance: 0.0,
+        ..Default::default()
+    };
+    assert!(difficulty.density_at(100) > difficulty.density_at(1));
+    assert_eq!(difficulty.density_at(1000), difficulty.max_density);
+    assert_eq!(difficulty.lives_at(0, 0), 1);
+    assert_eq!(difficulty.lives_at(4, 8), 5);
+    assert!(difficulty.lives_at(20, 1) > difficulty.lives_at(2, 1));
+    assert!(difficulty.lives_at(2, 30) > difficulty.lives_at(2, 1));
+
+    let mut board = Board::new(7, 30, 11);
+    board.difficulty = Difficulty {
+        density: 1.0,
+        max_density: 1.0,
+        min_empty: 2,
+        double_chance: 0.5,
+        coin_chance: 0.5,
+        ..Default::default()
+    };
+    for _ in 0..20 {
+        board.advance_row().unwrap();
+        let row = board.cells.row(29);
+        let lives = board.difficulty.lives_at(board.round, board.ball_count);
+        let blocks = row.iter().filter(|cell| cell.is_block()).count();
+        assert_eq!(blocks, 5);
+        assert_eq!(row.iter().filter(|&&cell| cell == Cell::Ball).count(), 1);
+        assert!(row.iter().all(|&cell| match cell {
+            Cell::Block { lives: block } => block == lives || block == 2 * lives,
+            _ => true,
+        }));
+    }
+    assert!(board
+        .blocks()
+        .iter()
+        .any(|&(_, lives)| lives > 1 && lives % 2 == 0));
+    assert!(count(&board, Cell::Coin) > 0);
+}
+
+#[test]
+fn daily_seed_is_the_date() {
+    assert_eq!(daily_seed(0), 19700101);
+    assert_eq!(daily_seed(10_956), 19991231);
+    assert_eq!(daily_seed(19_782), 20240229);
+    assert_eq!(daily_seed(19_783), 20240301);
+    assert_eq!(daily_seed(20_745), 20261019);
+}
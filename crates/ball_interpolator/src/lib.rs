@@ -1,14 +1,294 @@
-pub fn add(left: usize, right: usize) -> usize {
-    left + right
+use ball_simulation::{Event, EventType, SimulationState};
+use nalgebra::Vector2;
+
+/// A ball moving in a straight line from `time` on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    pub time: f64,
+    pub position: Vector2<f64>,
+    pub velocity: Vector2<f64>,
+}
+
+impl Keyframe {
+    pub fn position_at(&self, time: f64) -> Vector2<f64> {
+        self.position + self.velocity * (time - self.time)
+    }
+}
+
+/// Path of one ball, from when it was spawned until it was removed.
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub radius: f64,
+    keyframes: Vec<Keyframe>,
+    removed: Option<f64>,
+}
+
+impl Track {
+    fn new(ball: &ball_simulation::Ball, time: f64) -> Self {
+        Self {
+            radius: ball.radius,
+            keyframes: vec![Keyframe {
+                time,
+                position: ball.position,
+                velocity: ball.velocity,
+            }],
+            removed: None,
+        }
+    }
+
+    pub fn spawned(&self) -> f64 {
+        self.keyframes[0].time
+    }
+
+    pub fn removed(&self) -> Option<f64> {
+        self.removed
+    }
+
+    /// Every change of velocity, oldest first. Their positions are the
+    /// corners of the path.
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn is_alive_at(&self, time: f64) -> bool {
+        time >= self.spawned()
+            && match self.removed {
+                Some(removed) => time < removed,
+                None => true,
+            }
+    }
+
+    pub fn keyframe_at(&self, time: f64) -> Option<&Keyframe> {
+        self.is_alive_at(time).then(|| {
+            let index = self
+                .keyframes
+                .partition_point(|keyframe| keyframe.time <= time);
+            &self.keyframes[index - 1]
+        })
+    }
+
+    /// Position at `time`, or `None` when the ball doesn't exist then. Times
+    /// after the latest event continue the ball in a straight line.
+    pub fn position_at(&self, time: f64) -> Option<Vector2<f64>> {
+        self.keyframe_at(time)
+            .map(|keyframe| keyframe.position_at(time))
+    }
+
+    /// Points of the path between `from` and `to` with their times: both
+    /// ends, clamped to the ball's life, and every bounce in between.
+    pub fn path(&self, from: f64, to: f64) -> Vec<(f64, Vector2<f64>)> {
+        let from = from.max(self.spawned());
+        let to = self.removed.map_or(to, |removed| to.min(removed));
+        if from >= to {
+            return vec![];
+        }
+
+        let start = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= from);
+        let mut path = vec![(from, self.keyframes[start - 1].position_at(from))];
+        let mut last = &self.keyframes[start - 1];
+        for keyframe in self.keyframes[start..]
+            .iter()
+            .take_while(|keyframe| keyframe.time < to)
+        {
+            path.push((keyframe.time, keyframe.position));
+            last = keyframe;
+        }
+        path.push((to, last.position_at(to)));
+        path
+    }
+
+    fn last(&self) -> &Keyframe {
+        self.keyframes.last().unwrap()
+    }
+}
+
+/// Answers where every ball is at any time of a run, built from its states
+/// and events as they come. Balls keep their track when others are removed,
+/// and balls added at the end of the state get a new one.
+#[derive(Debug, Clone, Default)]
+pub struct Interpolator {
+    tracks: Vec<Track>,
+    // Track of each ball in the latest state
+    alive: Vec<usize>,
+}
+
+impl Interpolator {
+    pub fn new(initial: &SimulationState) -> Self {
+        let mut interpolator = Self::default();
+        interpolator.update_balls(initial);
+        interpolator
+    }
+
+    /// Tracks in the order their balls appeared.
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// Track followed by ball `ball` of the latest state.
+    pub fn track_of(&self, ball: usize) -> Option<usize> {
+        self.alive.get(ball).copied()
+    }
+
+    /// Adds the state reached through `event`. The event's ball may have been
+    /// removed from it, by the response or afterwards.
+    pub fn push(&mut self, state: &SimulationState, event: &Event<EventType>) {
+        let time = state.time;
+
+        let removed = state.removed_ball(event, |index| {
+            self.alive
+                .get(index)
+                .map(|&track| self.tracks[track].last().position_at(time))
+        });
+        if let Some(index) = removed {
+            let track = &mut self.tracks[self.alive.remove(index)];
+            let last = *track.last();
+            track.keyframes.push(Keyframe {
+                time,
+                position: last.position_at(time),
+                velocity: last.velocity,
+            });
+            track.removed = Some(time);
+        }
+
+        self.update_balls(state);
+    }
+
+    fn update_balls(&mut self, state: &SimulationState) {
+        for (index, ball) in state.balls.iter().enumerate() {
+            match self.alive.get(index) {
+                Some(&track) => {
+                    let track = &mut self.tracks[track];
+                    if track.last().velocity != ball.velocity {
+                        track.keyframes.push(Keyframe {
+                            time: state.time,
+                            position: ball.position,
+                            velocity: ball.velocity,
+                        });
+                    }
+                }
+                None => {
+                    self.alive.push(self.tracks.len());
+                    self.tracks.push(Track::new(ball, state.time));
+                }
+            }
+        }
+    }
+
+    /// Index and position of every track alive at `time`.
+    pub fn positions_at(&self, time: f64) -> impl Iterator<Item = (usize, Vector2<f64>)> + '_ {
+        self.tracks
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, track)| Some((index, track.position_at(time)?)))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use approx::assert_relative_eq;
+    use ball_simulation::{Ball, CollisionData, CollisionType, WallType};
+
+    fn ball(x: f64, velocity: Vector2<f64>) -> Ball {
+        Ball {
+            position: Vector2::new(x, 0.5),
+            velocity,
+            radius: 0.05,
+            filter: Default::default(),
+        }
+    }
 
     #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn removed_balls_keep_others_on_their_track() {
+        let mut state = SimulationState {
+            time: 0.0,
+            space_width: 1.0,
+            space_height: 1.0,
+            balls: vec![
+                ball(0.2, Vector2::new(0.0, -1.0)),
+                ball(0.8, Vector2::new(0.0, 0.5)),
+            ],
+            ..Default::default()
+        };
+        let mut interpolator = Interpolator::new(&state);
+
+        let (next_state, event) = state.clone().next(&[]).unwrap();
+        state = next_state;
+        assert!(matches!(
+            event.data,
+            EventType::Collision(CollisionData {
+                ball: 0,
+                against: CollisionType::Wall(WallType::YNegative)
+            })
+        ));
+        state.balls.remove(0);
+        interpolator.push(&state, &event);
+        assert_eq!(interpolator.track_of(0), Some(1));
+
+        let spawn = Event {
+            time: 0.1,
+            data: EventType::Custom,
+        };
+        let (next_state, event) = state.clone().next(&[spawn]).unwrap();
+        state = next_state;
+        state.balls.push(ball(0.5, Vector2::new(1.0, 0.0)));
+        interpolator.push(&state, &event);
+
+        let tracks = interpolator.tracks();
+        assert_eq!(tracks.len(), 3);
+        assert_relative_eq!(tracks[0].removed().unwrap(), 0.45);
+        assert_relative_eq!(tracks[2].spawned(), 0.55);
+
+        assert_relative_eq!(tracks[0].position_at(0.2).unwrap(), Vector2::new(0.2, 0.3));
+        assert_eq!(tracks[0].position_at(0.5), None);
+        assert_relative_eq!(tracks[1].position_at(0.5).unwrap(), Vector2::new(0.8, 0.75));
+        assert_eq!(tracks[2].position_at(0.5), None);
+
+        let positions = interpolator.positions_at(0.6).collect::<Vec<_>>();
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0].0, 1);
+        assert_relative_eq!(positions[1].1, Vector2::new(0.55, 0.5));
+    }
+
+    #[test]
+    fn keyframes_follow_bounces() {
+        let mut state = SimulationState {
+            time: 0.0,
+            space_width: 1.0,
+            space_height: 1.0,
+            balls: vec![ball(0.5, Vector2::new(1.0, 0.0))],
+            ..Default::default()
+        };
+        let mut interpolator = Interpolator::new(&state);
+        for _ in 0..3 {
+            let (next_state, event) = state.next(&[]).unwrap();
+            state = next_state;
+            interpolator.push(&state, &event);
+        }
+
+        let track = &interpolator.tracks()[0];
+        let corners = track
+            .keyframes()
+            .iter()
+            .map(|keyframe| keyframe.position.x)
+            .collect::<Vec<_>>();
+        assert_eq!(corners.len(), 4);
+        assert_relative_eq!(corners[1], 0.95);
+        assert_relative_eq!(corners[2], 0.05);
+        assert_relative_eq!(track.position_at(1.0).unwrap(), Vector2::new(0.4, 0.5));
+        assert_relative_eq!(track.position_at(3.0).unwrap(), Vector2::new(0.2, 0.5));
+
+        let path = track.path(-1.0, 1.5);
+        assert_eq!(path.len(), 4);
+        assert_relative_eq!(path[0].0, 0.0);
+        assert_relative_eq!(path[1].1, Vector2::new(0.95, 0.5));
+        assert_relative_eq!(path[2].1, Vector2::new(0.05, 0.5));
+        assert_relative_eq!(path[3].1, Vector2::new(0.2, 0.5));
+        let path = track.path(0.5, 1.0);
+        assert_eq!(path.len(), 2);
+        assert_relative_eq!(path[0].1, Vector2::new(0.9, 0.5));
+        assert!(track.path(1.0, 1.0).is_empty());
     }
 }
@@ -0,0 +1,56 @@
+// Scenes shared by the benchmarks, each bench only uses some of them
+#![allow(dead_code)]
+
+use ball_simulation::{Ball, Block, CollisionFilter};
+use nalgebra::Vector2;
+
+pub const BALL_RADIUS: f64 = 0.02;
+
+// Top half of the game's 10 by 10 board, every cell filled
+pub fn game_blocks() -> Vec<Block> {
+    (5..10)
+        .flat_map(|y| (0..10).map(move |x| Block::grid_cell(10, 10, x, y, 0.04)))
+        .collect()
+}
+
+// Square grid laid out like the game board, filling about `density` of the
+// cells in a fixed pseudo random pattern
+pub fn board(size: usize, density: f64) -> Vec<Block> {
+    let half_size = 0.44 / (size as f64 + 1.0);
+    let mut seed = 0x2545f4914f6cdd1d_u64;
+
+    let mut blocks = vec![];
+    for y in 0..size {
+        for x in 0..size {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            if (seed % 1000) as f64 / 1000.0 < density {
+                blocks.push(Block::grid_cell(size, size, x, y, half_size));
+            }
+        }
+    }
+    blocks
+}
+
+pub fn launched_ball(angle: f64) -> Ball {
+    Ball {
+        position: Vector2::new(0.5, BALL_RADIUS),
+        velocity: Vector2::new(angle.cos(), angle.sin()) * 2.0,
+        radius: BALL_RADIUS,
+        filter: CollisionFilter::default(),
+    }
+}
+
+// Balls fanned out over the lower part of the board
+pub fn balls_in_flight(count: usize) -> Vec<Ball> {
+    (0..count)
+        .map(|i| {
+            let t = (i as f64 + 0.5) / count as f64;
+            Ball {
+                position: Vector2::new(0.05 + 0.9 * t, 0.05 + 0.1 * (i % 3) as f64),
+                ..launched_ball(0.3 + 2.5 * t)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use ball_simulation::{EventSearch, SimulationState};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn scene(ball_count: usize) -> SimulationState {
+    SimulationState {
+        balls: common::balls_in_flight(ball_count),
+        blocks: common::game_blocks().into(),
+        ..Default::default()
+    }
+}
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("event search");
+    for ball_count in [10, 100, 1000] {
+        for search in [EventSearch::Serial, EventSearch::Parallel] {
+            let state = SimulationState {
+                search,
+                ..scene(ball_count)
+            };
+            group.bench_with_input(
+                BenchmarkId::new(format!("{search:?}"), ball_count),
+                &state,
+                |b, state| b.iter(|| SimulationState::next(black_box(state.clone()), &[])),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -1,7 +1,68 @@
-use ball_simulation::{Ball, SimulationState};
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+mod common;
+
+use ball_simulation::{
+    Ball, CollisionData, CollisionFilter, CollisionHandler, CollisionResponse, CollisionType,
+    Event, EventType, SimulationState, WallType,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 use nalgebra::Vector2;
 
+const SPAWN_INTERVAL: f64 = 1.0 / 6.0;
+
+// Game rules for a shot: blocks lose a life per hit and balls leave through the floor
+struct Turn {
+    lives: Vec<usize>,
+}
+
+impl CollisionHandler for Turn {
+    fn handle(
+        &mut self,
+        _: &SimulationState,
+        event: &Event<EventType>,
+        response: &mut CollisionResponse,
+    ) {
+        if let EventType::Collision(CollisionData { against, .. }) = event.data {
+            match against {
+                CollisionType::Wall(WallType::YNegative) => response.remove_ball = true,
+                CollisionType::Block { index, .. } => {
+                    self.lives[index] -= 1;
+                    if self.lives[index] == 0 {
+                        self.lives.remove(index);
+                        response.remove_block = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Fires `ball_count` balls one after the other and runs until all are back
+fn play_turn(mut state: SimulationState, ball_count: usize, lives: usize) -> usize {
+    let mut turn = Turn {
+        lives: vec![lives; state.blocks.len()],
+    };
+    let mut balls_left = ball_count;
+    let mut events = 0;
+
+    loop {
+        let spawn_event = (balls_left > 0).then(|| Event {
+            time: ((state.time / SPAWN_INTERVAL).floor() + 1.0) * SPAWN_INTERVAL - state.time,
+            data: EventType::Custom,
+        });
+        let Some((next_state, event)) = state.next_with(spawn_event.as_slice(), &mut turn) else {
+            return events;
+        };
+        state = next_state;
+        events += 1;
+
+        if let EventType::Custom = event.data {
+            state.balls.push(common::launched_ball(1.2));
+            balls_left -= 1;
+        }
+    }
+}
+
 pub fn criterion_benchmark(c: &mut Criterion) {
     let mut balls = vec![];
 
@@ -11,21 +72,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
                 position: Vector2::new(0.1 * x as f64, 0.1 * y as f64),
                 velocity: Vector2::new(0.1 * y as f64, 0.1 * x as f64),
                 radius: 0.04,
-            });
-        }
-    }
-
-    let mut blocks = vec![];
-
-    for y in 6..=10 {
-        let y = y as f64 / 11.0;
-        for x in 1..=10 {
-            let x = x as f64 / 11.0;
-            blocks.push(ball_simulation::Block {
-                min_y: y - 0.04,
-                max_y: y + 0.04,
-                min_x: x - 0.04,
-                max_x: x + 0.04,
+                filter: CollisionFilter::default(),
             });
         }
     }
@@ -35,12 +82,66 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         space_width: 1.0,
         space_height: 1.0,
         balls,
-        blocks,
+        blocks: common::game_blocks().into(),
+        ..Default::default()
     };
     c.bench_function("simulation iteration", |b| {
         b.iter(|| SimulationState::next(black_box(state.clone()), &[]))
     });
 }
 
-criterion_group!(benches, criterion_benchmark);
+pub fn scaling_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("next event");
+    for ball_count in [1, 10, 100] {
+        for board_size in [7, 10, 14] {
+            for density in [0.25, 0.5, 0.9] {
+                let state = SimulationState {
+                    balls: common::balls_in_flight(ball_count),
+                    // Leave the bottom rows free for the balls
+                    blocks: common::board(board_size, density)
+                        .into_iter()
+                        .filter(|block| block.min_y > 0.3)
+                        .collect(),
+                    ..Default::default()
+                };
+                group.bench_with_input(
+                    BenchmarkId::from_parameter(format!(
+                        "{ball_count} balls/{} blocks/{board_size}x{board_size} at {density}",
+                        state.blocks.len()
+                    )),
+                    &state,
+                    |b, state| b.iter(|| SimulationState::next(black_box(state.clone()), &[])),
+                );
+            }
+        }
+    }
+    group.finish();
+}
+
+pub fn full_turn_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("full turn");
+    group.sample_size(10);
+    for (ball_count, lives) in [(10, 5), (50, 25), (100, 50)] {
+        let state = SimulationState {
+            blocks: common::board(10, 0.7)
+                .into_iter()
+                .filter(|block| block.min_y > 0.3)
+                .collect(),
+            ..Default::default()
+        };
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{ball_count} balls/{lives} lives")),
+            &state,
+            |b, state| b.iter(|| play_turn(black_box(state.clone()), ball_count, lives)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    criterion_benchmark,
+    scaling_benchmark,
+    full_turn_benchmark
+);
 criterion_main!(benches);
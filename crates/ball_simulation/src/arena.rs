@@ -0,0 +1,36 @@
+use nalgebra::Vector2;
+
+use crate::Arena;
+
+impl Arena {
+    // Edges oriented so their outward side faces the play area, which is what
+    // `segment_ball` collides against
+    pub(crate) fn segments(&self) -> Vec<[Vector2<f64>; 2]> {
+        outline(&self.boundary, true)
+            .chain(self.holes.iter().flat_map(|hole| outline(hole, false)))
+            .collect()
+    }
+}
+
+fn outline(
+    polygon: &[Vector2<f64>],
+    clockwise: bool,
+) -> impl Iterator<Item = [Vector2<f64>; 2]> + '_ {
+    let reverse = (signed_area(polygon) > 0.0) == clockwise;
+    (0..polygon.len()).map(move |i| {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % polygon.len()];
+        if reverse {
+            [b, a]
+        } else {
+            [a, b]
+        }
+    })
+}
+
+fn signed_area(polygon: &[Vector2<f64>]) -> f64 {
+    (0..polygon.len())
+        .map(|i| polygon[i].perp(&polygon[(i + 1) % polygon.len()]))
+        .sum::<f64>()
+        / 2.0
+}
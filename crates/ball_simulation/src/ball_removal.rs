@@ -0,0 +1,38 @@
+use nalgebra::Vector2;
+
+use crate::{CollisionData, Event, EventType, SensorData, SimulationState, StallData};
+
+impl Event<EventType> {
+    /// Ball the event is about, `None` for custom events.
+    pub fn ball(&self) -> Option<usize> {
+        match self.data {
+            EventType::Collision(CollisionData { ball, .. })
+            | EventType::Sensor(SensorData { ball, .. })
+            | EventType::Stalled(StallData { ball, .. }) => Some(ball),
+            EventType::Custom => None,
+        }
+    }
+}
+
+impl SimulationState {
+    /// Ball of the previous state that `event`, which led to this state, took
+    /// away. `expected_position` gives where a ball of the previous state would
+    /// be now had it carried on, or `None` for balls the caller doesn't follow.
+    ///
+    /// Handlers only remove the ball or block an event is about and spawn balls
+    /// at the end, so following the event indices is enough to tell balls apart
+    /// from one state to the next.
+    pub fn removed_ball(
+        &self,
+        event: &Event<EventType>,
+        expected_position: impl FnOnce(usize) -> Option<Vector2<f64>>,
+    ) -> Option<usize> {
+        let index = event.ball()?;
+        let expected = expected_position(index)?;
+        let continues = self
+            .balls
+            .get(index)
+            .is_some_and(|ball| (ball.position - expected).norm() <= 1e-9 * (1.0 + self.time));
+        (!continues).then_some(index)
+    }
+}
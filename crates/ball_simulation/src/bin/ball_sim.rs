@@ -0,0 +1,269 @@
+//! Runs a scene headlessly and writes its event trace.
+//!
+//! ```text
+//! ball_sim <scene.json|scene.ron> [--events N] [--floor] [--format jsonl|csv]
+//!          [--output FILE] [--frames DIR] [--animation FILE]
+//! ```
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use ball_simulation::{
+    AnimationOptions, CollisionData, CollisionResponse, CollisionType, Event, EventType,
+    SensorData, SensorEventType, SimulationState, StallData, WallType,
+};
+use serde::Serialize;
+
+const USAGE: &str = "usage: ball_sim <scene.json|scene.ron> [--events N] [--floor] \
+[--format jsonl|csv] [--output FILE] [--frames DIR] [--animation FILE]
+
+  --events N        stop after N events (default 1000)
+  --floor           remove balls that hit the bottom wall, as in the game
+  --format          trace format, JSON lines (default) or CSV
+  --output FILE     write the trace to FILE instead of stdout
+  --frames DIR      write an SVG of the state after every event into DIR
+  --animation FILE  write the whole run as one animated SVG";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    JsonLines,
+    Csv,
+}
+
+#[derive(Debug)]
+struct Options {
+    scene: PathBuf,
+    events: usize,
+    floor: bool,
+    format: Format,
+    output: Option<PathBuf>,
+    frames: Option<PathBuf>,
+    animation: Option<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut scene = None;
+    let mut options = Options {
+        scene: PathBuf::new(),
+        events: 1000,
+        floor: false,
+        format: Format::JsonLines,
+        output: None,
+        frames: None,
+        animation: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--events" => {
+                options.events = value()?
+                    .parse()
+                    .map_err(|_| "--events takes a number".to_string())?
+            }
+            "--floor" => options.floor = true,
+            "--format" => {
+                options.format = match value()?.as_str() {
+                    "jsonl" => Format::JsonLines,
+                    "csv" => Format::Csv,
+                    other => return Err(format!("unknown format {other}")),
+                }
+            }
+            "--output" => options.output = Some(value()?.into()),
+            "--frames" => options.frames = Some(value()?.into()),
+            "--animation" => options.animation = Some(value()?.into()),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            _ if scene.is_none() => scene = Some(arg.into()),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+
+    options.scene = scene.ok_or("missing scene file")?;
+    Ok(options)
+}
+
+fn load_scene(options: &Options) -> Result<SimulationState, String> {
+    let content = fs::read_to_string(&options.scene)
+        .map_err(|error| format!("{}: {error}", options.scene.display()))?;
+    let is_ron = options
+        .scene
+        .extension()
+        .is_some_and(|extension| extension == "ron");
+    if is_ron {
+        ron::from_str(&content).map_err(|error| format!("{}: {error}", options.scene.display()))
+    } else {
+        serde_json::from_str(&content)
+            .map_err(|error| format!("{}: {error}", options.scene.display()))
+    }
+}
+
+/// One line of the trace. The ball columns describe it after the event, and
+/// are empty when it was removed.
+#[derive(Debug, Serialize)]
+struct Record {
+    step: usize,
+    time: f64,
+    kind: &'static str,
+    ball: Option<usize>,
+    wall: Option<WallType>,
+    target: Option<usize>,
+    x: Option<f64>,
+    y: Option<f64>,
+    vx: Option<f64>,
+    vy: Option<f64>,
+    balls: usize,
+    blocks: usize,
+}
+
+impl Record {
+    fn new(step: usize, state: &SimulationState, event: &Event<EventType>, removed: bool) -> Self {
+        let (kind, ball, wall, target) = match event.data {
+            EventType::Collision(CollisionData { ball, against }) => match against {
+                CollisionType::Wall(wall) => ("wall", Some(ball), Some(wall), None),
+                CollisionType::Block { index, .. } => ("block", Some(ball), None, Some(index)),
+                CollisionType::Arena { edge, .. } => ("arena", Some(ball), None, Some(edge)),
+            },
+            EventType::Sensor(SensorData { ball, sensor, kind }) => (
+                match kind {
+                    SensorEventType::Enter => "sensor_enter",
+                    SensorEventType::Exit => "sensor_exit",
+                },
+                Some(ball),
+                None,
+                Some(sensor),
+            ),
+            EventType::Stalled(StallData { ball, .. }) => ("stalled", Some(ball), None, None),
+            EventType::Custom => ("custom", None, None, None),
+        };
+        let after = ball
+            .filter(|_| !removed)
+            .and_then(|ball| state.balls.get(ball));
+
+        Self {
+            step,
+            time: state.time,
+            kind,
+            ball,
+            wall,
+            target,
+            x: after.map(|ball| ball.position.x),
+            y: after.map(|ball| ball.position.y),
+            vx: after.map(|ball| ball.velocity.x),
+            vy: after.map(|ball| ball.velocity.y),
+            balls: state.balls.len(),
+            blocks: state.blocks.len(),
+        }
+    }
+}
+
+enum Trace<W: Write> {
+    JsonLines(W),
+    Csv(Box<csv::Writer<W>>),
+}
+
+impl<W: Write> Trace<W> {
+    fn write(&mut self, record: &Record) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Self::JsonLines(output) => {
+                serde_json::to_writer(&mut *output, record)?;
+                writeln!(output)?;
+            }
+            Self::Csv(output) => output.serialize(record)?,
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::JsonLines(output) => output.flush(),
+            Self::Csv(output) => output.flush(),
+        }
+    }
+}
+
+fn run(options: Options) -> Result<(), Box<dyn std::error::Error>> {
+    let initial = load_scene(&options)?;
+
+    let output: Box<dyn Write> = match &options.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let output = BufWriter::new(output);
+    let mut trace = match options.format {
+        Format::JsonLines => Trace::JsonLines(output),
+        Format::Csv => Trace::Csv(Box::new(csv::Writer::from_writer(output))),
+    };
+
+    if let Some(frames) = &options.frames {
+        fs::create_dir_all(frames)?;
+    }
+
+    let removes_ball = |event: &Event<EventType>| {
+        options.floor
+            && matches!(
+                event.data,
+                EventType::Collision(CollisionData {
+                    against: CollisionType::Wall(WallType::YNegative),
+                    ..
+                })
+            )
+    };
+    let mut handler =
+        |_: &SimulationState, event: &Event<EventType>, response: &mut CollisionResponse| {
+            response.remove_ball = removes_ball(event);
+        };
+
+    let mut steps = vec![];
+    let mut state = initial.clone();
+    for step in 0..options.events {
+        let Some((next_state, event)) = state.next_with(&[], &mut handler) else {
+            break;
+        };
+        state = next_state;
+
+        trace.write(&Record::new(step, &state, &event, removes_ball(&event)))?;
+
+        if let Some(frames) = &options.frames {
+            let document = state.save_img(svg::Document::new(), 0);
+            svg::save(frames.join(format!("frame_{step:05}.svg")), &document)?;
+        }
+        if options.animation.is_some() {
+            steps.push((state.clone(), event));
+        }
+    }
+
+    trace.flush()?;
+
+    if let Some(path) = &options.animation {
+        let document = initial.save_animation(&steps, &AnimationOptions::default());
+        svg::save(path, &document)?;
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            if !error.is_empty() {
+                eprintln!("{error}");
+            }
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
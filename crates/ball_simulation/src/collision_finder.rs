@@ -1,61 +1,127 @@
+use std::cmp::Ordering;
+
+use nalgebra::Vector2;
+
 use crate::{
-    collision_times::{earliest_collision_ball_block, earliest_collision_ball_walls},
-    EventType,
+    collision_times::{
+        earliest_collision_ball_arena, earliest_collision_ball_block,
+        earliest_collision_ball_walls, earliest_event_ball_sensor,
+    },
+    Arena, EventType, SensorData,
 };
-use itertools::Itertools;
 
 use crate::{CollisionData, CollisionType, Event, SimulationState};
 
+// Earliest time first, lowest ball index on ties, so every search order agrees
+fn event_order(
+    (ball_a, a): &(usize, Event<EventType>),
+    (ball_b, b): &(usize, Event<EventType>),
+) -> Ordering {
+    a.time.total_cmp(&b.time).then(ball_a.cmp(ball_b))
+}
+
 impl SimulationState {
     pub(crate) fn earliest_event(
-        &mut self,
+        &self,
         custom_events: &[Event<EventType>],
     ) -> Option<Event<EventType>> {
+        let arena_segments = self.arena.as_ref().map(Arena::segments);
+        let ball_event = |ball_index: usize| {
+            self.earliest_ball_event(ball_index, arena_segments.as_deref())
+                .map(|event| (ball_index, event))
+        };
+
+        let balls = 0..self.balls.len();
+        let earliest_ball_event = match self.search {
+            #[cfg(feature = "rayon")]
+            crate::EventSearch::Parallel => {
+                use rayon::prelude::*;
+                balls
+                    .into_par_iter()
+                    .filter_map(ball_event)
+                    .min_by(event_order)
+            }
+            _ => balls.filter_map(ball_event).min_by(event_order),
+        };
+
         custom_events
             .iter()
             .copied()
-            .chain(
-                self.balls
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(ball_index, ball)| {
-                        earliest_collision_ball_walls(ball, self.space_width, self.space_height)
-                            .map(|Event { time, data }| Event {
-                                time,
-                                data: CollisionData {
-                                    ball: ball_index,
-                                    against: CollisionType::Wall(data),
-                                },
-                            })
-                    })
-                    .chain(
-                        self.balls
-                            .iter()
-                            .enumerate()
-                            .cartesian_product(self.blocks.iter().enumerate())
-                            .filter_map(|((ball_index, ball), (block_index, block))| {
-                                earliest_collision_ball_block(ball, block).map(
-                                    |Event {
-                                         time,
-                                         data: contact_position,
-                                     }| Event {
-                                        time,
-                                        data: CollisionData {
-                                            ball: ball_index,
-                                            against: CollisionType::Block {
-                                                index: block_index,
-                                                contact_position,
-                                            },
-                                        },
-                                    },
-                                )
-                            }),
+            .chain(earliest_ball_event.map(|(_, event)| event))
+            .min_by(|a, b| a.time.total_cmp(&b.time))
+    }
+
+    fn earliest_ball_event(
+        &self,
+        ball_index: usize,
+        arena_segments: Option<&[[Vector2<f64>; 2]]>,
+    ) -> Option<Event<EventType>> {
+        let ball = &self.balls[ball_index];
+
+        match arena_segments {
+            Some(segments) => earliest_collision_ball_arena(ball, segments).map(
+                |Event {
+                     time,
+                     data: (edge, contact_position),
+                 }| Event {
+                    time,
+                    data: CollisionType::Arena {
+                        edge,
+                        contact_position,
+                    },
+                },
+            ),
+            None => earliest_collision_ball_walls(ball, self.space_width, self.space_height).map(
+                |Event { time, data }| Event {
+                    time,
+                    data: CollisionType::Wall(data),
+                },
+            ),
+        }
+        .into_iter()
+        .chain(
+            self.blocks
+                .iter()
+                .enumerate()
+                .filter(|(_, block)| ball.filter.collides_with(&block.filter))
+                .filter_map(|(block_index, block)| {
+                    earliest_collision_ball_block(ball, block).map(
+                        |Event {
+                             time,
+                             data: contact_position,
+                         }| Event {
+                            time,
+                            data: CollisionType::Block {
+                                index: block_index,
+                                contact_position,
+                            },
+                        },
                     )
-                    .map(|Event { time, data }| Event {
+                }),
+        )
+        .map(|Event { time, data }| Event {
+            time,
+            data: EventType::Collision(CollisionData {
+                ball: ball_index,
+                against: data,
+            }),
+        })
+        .chain(
+            self.sensors
+                .iter()
+                .enumerate()
+                .filter(|(_, sensor)| ball.filter.collides_with(&sensor.filter))
+                .filter_map(|(sensor_index, sensor)| {
+                    earliest_event_ball_sensor(ball, sensor).map(|Event { time, data }| Event {
                         time,
-                        data: EventType::Collision(data),
-                    }),
-            )
-            .min_by(|a, b| a.time.total_cmp(&b.time))
+                        data: EventType::Sensor(SensorData {
+                            ball: ball_index,
+                            sensor: sensor_index,
+                            kind: data,
+                        }),
+                    })
+                }),
+        )
+        .min_by(|a, b| a.time.total_cmp(&b.time))
     }
 }
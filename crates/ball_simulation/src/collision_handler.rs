@@ -0,0 +1,137 @@
+use nalgebra::Vector2;
+
+use crate::{
+    stall_detection::nudge, Ball, CollisionData, CollisionType, Event, EventType, SimulationState,
+    StallData, StallDetection, StallPolicy, WallType,
+};
+
+/// Outcome of an event, prefilled with the default response and open to
+/// changes by a [`CollisionHandler`].
+#[derive(Debug, Clone, Default)]
+pub struct CollisionResponse {
+    /// Velocity the ball involved in the event leaves with, `None` for events
+    /// without a ball.
+    pub velocity: Option<Vector2<f64>>,
+    pub remove_ball: bool,
+    pub remove_block: bool,
+    pub remove_sensor: bool,
+    /// Balls added at the end of `SimulationState::balls`.
+    pub spawn: Vec<Ball>,
+}
+
+/// Hook run on every event produced by `SimulationState::next_with`, after the
+/// simulation has advanced to the event time and before the response is
+/// applied. Removals are applied block and sensor first, then the ball, and
+/// spawned balls are pushed last, so the indices in the event stay valid for
+/// the whole call.
+pub trait CollisionHandler {
+    fn handle(
+        &mut self,
+        state: &SimulationState,
+        event: &Event<EventType>,
+        response: &mut CollisionResponse,
+    );
+}
+
+impl CollisionHandler for () {
+    fn handle(&mut self, _: &SimulationState, _: &Event<EventType>, _: &mut CollisionResponse) {}
+}
+
+impl<F> CollisionHandler for F
+where
+    F: FnMut(&SimulationState, &Event<EventType>, &mut CollisionResponse),
+{
+    fn handle(
+        &mut self,
+        state: &SimulationState,
+        event: &Event<EventType>,
+        response: &mut CollisionResponse,
+    ) {
+        self(state, event, response)
+    }
+}
+
+impl SimulationState {
+    pub(crate) fn default_response(&self, event: &Event<EventType>) -> CollisionResponse {
+        let velocity = match event.data {
+            EventType::Collision(CollisionData { ball, against }) => {
+                let ball = &self.balls[ball];
+                let mut velocity = ball.velocity;
+                match against {
+                    CollisionType::Wall(wall_type) => match wall_type {
+                        WallType::YPositive | WallType::YNegative => velocity.y *= -1.0,
+                        WallType::XNegative | WallType::XPositive => velocity.x *= -1.0,
+                    },
+                    CollisionType::Block {
+                        contact_position, ..
+                    }
+                    | CollisionType::Arena {
+                        contact_position, ..
+                    } => {
+                        let contact_normal = (contact_position - ball.position).normalize();
+                        let normal_velocity = velocity.dot(&contact_normal) * contact_normal;
+                        velocity -= 2.0 * normal_velocity;
+                    }
+                }
+                Some(velocity)
+            }
+            EventType::Sensor(sensor) => Some(self.balls[sensor.ball].velocity),
+            EventType::Stalled(StallData { ball, reason }) => {
+                let velocity = self.balls[ball].velocity;
+                match self.stall_detection {
+                    Some(StallDetection {
+                        policy: StallPolicy::Nudge,
+                        min_angle,
+                        ..
+                    }) => Some(nudge(velocity, reason, min_angle)),
+                    _ => Some(velocity),
+                }
+            }
+            EventType::Custom => None,
+        };
+
+        let remove_ball = match (event.data, self.stall_detection) {
+            (EventType::Stalled(_), Some(settings)) => settings.policy == StallPolicy::RemoveBall,
+            _ => false,
+        };
+
+        CollisionResponse {
+            velocity,
+            remove_ball,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn apply_response(&mut self, event: &Event<EventType>, response: CollisionResponse) {
+        let (ball, block, sensor) = event_targets(event);
+
+        if let (Some(ball), Some(velocity)) = (ball, response.velocity) {
+            self.balls[ball].velocity = velocity;
+        }
+        if let (Some(block), true) = (block, response.remove_block) {
+            self.blocks.remove(block);
+        }
+        if let (Some(sensor), true) = (sensor, response.remove_sensor) {
+            self.sensors.remove(sensor);
+        }
+        if let (Some(ball), true) = (ball, response.remove_ball) {
+            self.balls.remove(ball);
+        }
+        self.balls.extend(response.spawn);
+    }
+}
+
+// Ball, block and sensor an event is about
+pub(crate) fn event_targets(
+    event: &Event<EventType>,
+) -> (Option<usize>, Option<usize>, Option<usize>) {
+    match event.data {
+        EventType::Collision(CollisionData { ball, against }) => match against {
+            CollisionType::Block { index, .. } => (Some(ball), Some(index), None),
+            CollisionType::Wall(_) | CollisionType::Arena { .. } => (Some(ball), None, None),
+        },
+        EventType::Sensor(sensor) => (Some(sensor.ball), None, Some(sensor.sensor)),
+        EventType::Stalled(stall) => (Some(stall.ball), None, None),
+        EventType::Custom => (None, None, None),
+    }
+}
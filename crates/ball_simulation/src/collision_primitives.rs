@@ -1,6 +1,6 @@
 use nalgebra::Vector2;
 
-use crate::{Ball, Event};
+use crate::{Ball, CollisionFilter, Event};
 
 pub(crate) fn ball_ball(b1: &Ball, b2: &Ball) -> Option<f64> {
     let delta_v = b2.velocity - b1.velocity;
@@ -20,6 +20,7 @@ fn ball_point(ball: &Ball, point: Vector2<f64>) -> Option<f64> {
             position: point,
             velocity: Vector2::zeros(),
             radius: 0.0,
+            filter: CollisionFilter::default(),
         },
     )
 }
@@ -51,14 +52,23 @@ fn segment_ball_time(
     }
 }
 
-const CLEARANCE: f64 = 0.0001;
+pub(crate) const CLEARANCE: f64 = 0.0001;
 
-// Calculates collision point and time between a moving ball and a segment from segment_a to segment_b
+// Calculates collision point and time between a moving ball and a segment from segment_a to segment_b,
+// ends marked false in `corners` are continued by another segment and never hit on their own,
+// `passable` when the segment belongs to a shape the ball can get into through another, non-solid edge
 pub(crate) fn segment_ball(
     segment_a: Vector2<f64>,
     segment_b: Vector2<f64>,
+    corners: [bool; 2],
+    passable: bool,
     ball: &Ball,
 ) -> Option<Event<Vector2<f64>>> {
+    let corner = |enabled: bool, point: Vector2<f64>| {
+        enabled
+            .then(|| ball_point(ball, point).map(|time| Event { time, data: point }))
+            .flatten()
+    };
     let ab = segment_b - segment_a;
     let ab_mag = ab.magnitude();
     let signed_distance = (ball.position - segment_a).perp(&ab) / ab_mag;
@@ -68,43 +78,34 @@ pub(crate) fn segment_ball(
     } else if signed_distance < ball.radius {
         let ab_proj = (ball.position - segment_a).dot(&ab) / ab_mag.powi(2);
         if ab_proj < 0.0 {
-            ball_point(ball, segment_a).map(|time| Event {
-                time,
-                data: segment_a,
-            })
+            corner(corners[0], segment_a)
         } else if ab_proj > 1.0 {
-            ball_point(ball, segment_b).map(|time| Event {
-                time,
-                data: segment_b,
-            })
+            corner(corners[1], segment_b)
+        } else if normal_velocity >= 0.0 {
+            // Touching and moving away, or crossing from behind a one-way edge
+            None
         } else if signed_distance > ball.radius - CLEARANCE {
-            if normal_velocity >= 0.0 {
-                None
-            } else {
-                Some(Event {
-                    time: 0.0,
-                    data: segment_a + ab_proj * ab,
-                })
-            }
+            Some(Event {
+                time: 0.0,
+                data: segment_a + ab_proj * ab,
+            })
         } else {
-            panic!("already in collision");
+            // Only a ball that came in through a non-solid edge can overlap a
+            // solid one, it keeps going while it straddles the edge
+            debug_assert!(passable, "already in collision");
+            None
         }
     } else {
         let alpha = segment_ball_alpha(segment_a, segment_b, ball);
         if alpha < 0.0 {
-            ball_point(ball, segment_a).map(|time| Event {
-                time,
-                data: segment_a,
-            })
+            corner(corners[0], segment_a)
         } else if alpha > 1.0 {
-            ball_point(ball, segment_b).map(|time| Event {
-                time,
-                data: segment_b,
-            })
+            corner(corners[1], segment_b)
         } else if normal_velocity >= 0.0 {
             None
         } else {
-            let time = segment_ball_time(segment_a, segment_b, ball, alpha);
+            // Already touching up to rounding, which can make the time slightly negative
+            let time = segment_ball_time(segment_a, segment_b, ball, alpha).max(0.0);
             Some(Event {
                 time,
                 data: segment_a + alpha * (segment_b - segment_a),
@@ -121,7 +122,7 @@ mod tests {
 
     use crate::{
         collision_primitives::{segment_ball, segment_ball_time},
-        Ball, Event,
+        Ball, CollisionFilter, Event,
     };
 
     use super::segment_ball_alpha;
@@ -134,13 +135,14 @@ mod tests {
             position: Vector2::new(8.0, -4.0),
             velocity: Vector2::new(-1.0, 1.0),
             radius: 0.5,
+            filter: CollisionFilter::default(),
         };
 
         assert_eq!(segment_ball_alpha(segment_a, segment_b, &ball), 0.875);
 
         assert_eq!(segment_ball_time(segment_a, segment_b, &ball, 0.875), 2.5);
 
-        let collision = segment_ball(segment_a, segment_b, &ball);
+        let collision = segment_ball(segment_a, segment_b, [true, true], false, &ball);
 
         assert!(collision.is_some());
         assert_eq!(
@@ -160,14 +162,16 @@ mod tests {
             position: Vector2::new(1.0, 1.0),
             velocity: Vector2::new(0.0, 1.0),
             radius: 0.5,
+            filter: CollisionFilter::default(),
         };
         let ball2 = Ball {
             position: Vector2::new(6.0, 3.0),
             velocity: Vector2::new(-1.0, 1.0),
             radius: 1.0,
+            filter: CollisionFilter::default(),
         };
 
-        let collision1 = segment_ball(segment_a, segment_b, &ball1);
+        let collision1 = segment_ball(segment_a, segment_b, [true, true], false, &ball1);
 
         assert!(collision1.is_some());
         assert_eq!(
@@ -178,7 +182,7 @@ mod tests {
             }
         );
 
-        let collision2 = segment_ball(segment_a, segment_b, &ball2);
+        let collision2 = segment_ball(segment_a, segment_b, [true, true], false, &ball2);
 
         assert!(collision2.is_some());
         assert_eq!(collision2.unwrap().data, segment_b);
@@ -192,12 +196,46 @@ mod tests {
             position: Vector2::new(-2.0, -0.5),
             velocity: Vector2::new(1.0, 0.0),
             radius: 1.0,
+            filter: CollisionFilter::default(),
         };
 
-        let collision = segment_ball(segment_a, segment_b, &ball);
+        let collision = segment_ball(segment_a, segment_b, [true, true], false, &ball);
 
         assert!(collision.is_some());
 
         assert_relative_eq!(collision.unwrap().data, segment_a);
     }
+
+    #[test]
+    fn test_segment_ball_overlapping_passable() {
+        let segment_a = Vector2::new(0.0, 0.0);
+        let segment_b = Vector2::new(2.0, 0.0);
+        let ball = Ball {
+            position: Vector2::new(1.0, -0.2),
+            velocity: Vector2::new(0.0, 1.0),
+            radius: 0.5,
+            filter: CollisionFilter::default(),
+        };
+
+        assert_eq!(
+            segment_ball(segment_a, segment_b, [true, true], true, &ball),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "already in collision")]
+    fn test_segment_ball_overlapping_solid() {
+        let segment_a = Vector2::new(0.0, 0.0);
+        let segment_b = Vector2::new(2.0, 0.0);
+        let ball = Ball {
+            position: Vector2::new(1.0, -0.2),
+            velocity: Vector2::new(0.0, 1.0),
+            radius: 0.5,
+            filter: CollisionFilter::default(),
+        };
+
+        segment_ball(segment_a, segment_b, [true, true], false, &ball);
+    }
 }
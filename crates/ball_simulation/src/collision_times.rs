@@ -1,8 +1,8 @@
 use nalgebra::Vector2;
 
 use crate::{
-    collision_primitives::{ball_ball, segment_ball},
-    Ball, Block, Event, WallType,
+    collision_primitives::{ball_ball, segment_ball, CLEARANCE},
+    Ball, Block, BlockEdges, CollisionFilter, Event, Sensor, SensorEventType, WallType,
 };
 
 pub(crate) fn earliest_collision_ball_walls(
@@ -46,6 +46,24 @@ pub(crate) fn earliest_collision_ball_walls(
         .min_by(|a, b| a.time.partial_cmp(&b.time).unwrap())
 }
 
+pub(crate) fn earliest_collision_ball_arena(
+    ball: &Ball,
+    segments: &[[Vector2<f64>; 2]],
+) -> Option<Event<(usize, Vector2<f64>)>> {
+    segments
+        .iter()
+        .enumerate()
+        .filter_map(|(edge, &[segment_a, segment_b])| {
+            segment_ball(segment_a, segment_b, [true, true], false, ball).map(
+                |Event { time, data }| Event {
+                    time,
+                    data: (edge, data),
+                },
+            )
+        })
+        .min_by(|a, b| a.time.total_cmp(&b.time))
+}
+
 pub(crate) fn earliest_collision_ball_block(
     ball: &Ball,
     block: &Block,
@@ -55,6 +73,9 @@ pub(crate) fn earliest_collision_ball_block(
         max_y,
         max_x,
         min_y,
+        solid,
+        seams,
+        ..
     } = *block;
     let center = Vector2::new((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
     let tl = Vector2::new(min_x, max_y);
@@ -68,15 +89,118 @@ pub(crate) fn earliest_collision_ball_block(
             position: center,
             velocity: Vector2::zeros(),
             radius,
+            filter: CollisionFilter::default(),
         },
     )
     .is_some()
         || (ball.position - center).magnitude_squared() <= (radius + ball.radius).powi(2))
     .then(|| {
-        [[tr, tl], [tl, bl], [bl, br], [br, tr]]
-            .into_iter()
-            .filter_map(|[segment_a, segment_b]| segment_ball(segment_a, segment_b, ball))
-            .min_by(|a, b| a.time.total_cmp(&b.time))
+        [
+            (
+                solid.top && !seams.top,
+                [tr, tl],
+                [seams.top_right, seams.top_left],
+            ),
+            (
+                solid.left && !seams.left,
+                [tl, bl],
+                [seams.top_left, seams.bottom_left],
+            ),
+            (
+                solid.bottom && !seams.bottom,
+                [bl, br],
+                [seams.bottom_left, seams.bottom_right],
+            ),
+            (
+                solid.right && !seams.right,
+                [br, tr],
+                [seams.bottom_right, seams.top_right],
+            ),
+        ]
+        .into_iter()
+        .filter(|&(solid, ..)| solid)
+        .filter_map(|(_, [segment_a, segment_b], [seam_a, seam_b])| {
+            segment_ball(
+                segment_a,
+                segment_b,
+                [!seam_a, !seam_b],
+                solid != BlockEdges::ALL,
+                ball,
+            )
+        })
+        .min_by(|a, b| a.time.total_cmp(&b.time))
     })
     .flatten()
 }
+
+pub(crate) fn earliest_event_ball_sensor(
+    ball: &Ball,
+    sensor: &Sensor,
+) -> Option<Event<SensorEventType>> {
+    let Sensor {
+        max_y,
+        min_x,
+        max_x,
+        min_y,
+        ..
+    } = *sensor;
+    let closest = Vector2::new(
+        ball.position.x.clamp(min_x, max_x),
+        ball.position.y.clamp(min_y, max_y),
+    );
+    let offset = ball.position - closest;
+    let distance = offset.magnitude();
+    let inside = distance < ball.radius - CLEARANCE
+        || (distance <= ball.radius + CLEARANCE && ball.velocity.dot(&offset) < 0.0);
+
+    if !inside {
+        return earliest_collision_ball_block(ball, &Block::new(max_y, min_y, min_x, max_x)).map(
+            |Event { time, .. }| Event {
+                time,
+                data: SensorEventType::Enter,
+            },
+        );
+    }
+
+    // Leave the sensor grown by the ball radius, then correct for its rounded corners
+    let exit_time = |position: f64, velocity: f64, min: f64, max: f64| {
+        if velocity > 0.0 {
+            (max + ball.radius - position) / velocity
+        } else if velocity < 0.0 {
+            (min - ball.radius - position) / velocity
+        } else {
+            f64::INFINITY
+        }
+    };
+    let time = exit_time(ball.position.x, ball.velocity.x, min_x, max_x).min(exit_time(
+        ball.position.y,
+        ball.velocity.y,
+        min_y,
+        max_y,
+    ));
+    if !time.is_finite() {
+        return None;
+    }
+
+    let exit = ball.position + ball.velocity * time;
+    let corner = Vector2::new(exit.x.clamp(min_x, max_x), exit.y.clamp(min_y, max_y));
+    let time = if corner.x != exit.x && corner.y != exit.y {
+        let relative = ball.position - corner;
+        let a = ball.velocity.magnitude_squared();
+        let b = ball.velocity.dot(&relative);
+        let c = relative.magnitude_squared() - ball.radius.powi(2);
+        let d = b.powi(2) - a * c;
+        if d >= 0.0 {
+            (-b + d.sqrt()) / a
+        } else {
+            time
+        }
+    } else {
+        time
+    };
+
+    Some(Event {
+        time: time.max(0.0),
+        data: SensorEventType::Exit,
+    })
+}
@@ -0,0 +1,74 @@
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use nalgebra::Vector2;
+
+use crate::{Ball, CollisionFilter, Event, EventType};
+
+/// Launches balls one after the other from a point on the floor, at a fixed
+/// speed and never flatter than `min_angle`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Emitter {
+    pub position: Vector2<f64>,
+    /// Aimed direction, clamped when launching.
+    pub direction: Vector2<f64>,
+    pub speed: f64,
+    pub radius: f64,
+    /// Simulation time between two launches.
+    pub interval: f64,
+    /// Smallest angle from the floor, in radians.
+    pub min_angle: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub filter: CollisionFilter,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self {
+            position: Vector2::new(0.5, 0.0),
+            direction: Vector2::new(0.0, 1.0),
+            speed: 2.0,
+            radius: 0.02,
+            interval: 1.0 / 6.0,
+            min_angle: 0.15,
+            filter: CollisionFilter::default(),
+        }
+    }
+}
+
+impl Emitter {
+    /// Unit vector pointing up at least `min_angle` away from both sides of
+    /// the floor, as close to `direction` as possible. Directions below the
+    /// floor go to the nearest side.
+    pub fn clamp_direction(direction: Vector2<f64>, min_angle: f64) -> Vector2<f64> {
+        let angle = direction.y.atan2(direction.x);
+        let angle = if angle < -FRAC_PI_2 {
+            PI - min_angle
+        } else {
+            angle.clamp(min_angle, PI - min_angle)
+        };
+        Vector2::new(angle.cos(), angle.sin())
+    }
+
+    pub fn launch_direction(&self) -> Vector2<f64> {
+        Self::clamp_direction(self.direction, self.min_angle)
+    }
+
+    pub fn ball(&self) -> Ball {
+        Ball {
+            position: self.position,
+            velocity: self.launch_direction() * self.speed,
+            radius: self.radius,
+            filter: self.filter,
+        }
+    }
+
+    /// Custom event for the first launch strictly after `time`, launches
+    /// happening at every multiple of `interval`.
+    pub fn next_launch(&self, time: f64) -> Event<EventType> {
+        Event {
+            time: ((time / self.interval).floor() + 1.0) * self.interval - time,
+            data: EventType::Custom,
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    collision_handler::event_targets, Ball, Block, CollisionHandler, CollisionResponse, Event,
+    EventType, Sensor, SimulationState,
+};
+
+// Everything an event changed besides moving the balls forward
+#[derive(Debug, Clone)]
+struct Step {
+    event: Event<EventType>,
+    // Event ball as it was before the response
+    ball: Option<(usize, Ball)>,
+    removed_ball: bool,
+    removed_block: Option<(usize, Block)>,
+    removed_sensor: Option<(usize, Sensor)>,
+    spawned: usize,
+}
+
+/// Events taken with [`SimulationState::next_recorded`], which
+/// [`SimulationState::prev`] undoes newest first.
+#[derive(Debug, Clone, Default)]
+pub struct EventHistory {
+    steps: Vec<Step>,
+}
+
+impl EventHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+    }
+
+    /// Recorded events, oldest first.
+    pub fn events(&self) -> impl Iterator<Item = &Event<EventType>> {
+        self.steps.iter().map(|step| &step.event)
+    }
+}
+
+impl SimulationState {
+    /// Same as [`SimulationState::next_with`], also recording what the event
+    /// changed into `history`.
+    pub fn next_recorded(
+        self,
+        custom_events: &[Event<EventType>],
+        handler: &mut impl CollisionHandler,
+        history: &mut EventHistory,
+    ) -> Option<(SimulationState, Event<EventType>)> {
+        self.advance(
+            custom_events,
+            handler,
+            |state: &SimulationState, event: &Event<EventType>, response: &CollisionResponse| {
+                let (ball, block, sensor) = event_targets(event);
+                history.steps.push(Step {
+                    event: *event,
+                    ball: ball.map(|ball| (ball, state.balls[ball])),
+                    removed_ball: ball.is_some() && response.remove_ball,
+                    removed_block: block
+                        .filter(|_| response.remove_block)
+                        .map(|block| (block, state.blocks[block])),
+                    removed_sensor: sensor
+                        .filter(|_| response.remove_sensor)
+                        .map(|sensor| (sensor, state.sensors[sensor])),
+                    spawned: response.spawn.len(),
+                });
+            },
+        )
+    }
+
+    /// Steps back to the previous event by undoing the latest event in
+    /// `history` and moving every ball backwards along its path. Returns the
+    /// undone event, or `None` when there is nothing left to undo. Changes
+    /// made to the state outside of `next_recorded` are not undone.
+    pub fn prev(
+        mut self,
+        history: &mut EventHistory,
+    ) -> Option<(SimulationState, Event<EventType>)> {
+        let step = history.steps.pop()?;
+
+        self.balls.truncate(self.balls.len() - step.spawned);
+        if let Some((index, ball)) = step.ball {
+            if step.removed_ball {
+                self.balls.insert(index, ball);
+            } else {
+                self.balls[index] = ball;
+            }
+        }
+        if let Some((index, sensor)) = step.removed_sensor {
+            self.sensors.insert(index, sensor);
+        }
+        if let Some((index, block)) = step.removed_block {
+            self.blocks.insert(index, block);
+        }
+        self.forward(-step.event.time);
+
+        Some((self, step.event))
+    }
+}
@@ -1,24 +1,150 @@
 #![feature(assert_matches)]
+mod arena;
+mod ball_removal;
 mod collision_finder;
+mod collision_handler;
 mod collision_primitives;
 mod collision_times;
+mod emitter;
+mod history;
+mod snapshot;
+mod stall_detection;
+mod static_geometry;
+#[cfg(feature = "svg")]
+mod svg_animation;
+#[cfg(feature = "svg")]
+mod svg_import;
 #[cfg(test)]
 mod tests;
+pub use collision_handler::{CollisionHandler, CollisionResponse};
+pub use emitter::Emitter;
+pub use history::EventHistory;
 use nalgebra::Vector2;
+pub use snapshot::{History, Snapshot};
+pub use stall_detection::{StallData, StallDetection, StallPolicy, StallReason, StallTracker};
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+#[cfg(feature = "svg")]
+pub use svg_animation::AnimationOptions;
+#[cfg(feature = "svg")]
+pub use svg_import::SvgImportError;
+
+/// Decides which balls and blocks interact. Two objects collide only when
+/// each one's `layers` intersect the other's `mask`; walls always collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CollisionFilter {
+    pub layers: u32,
+    pub mask: u32,
+}
+
+impl CollisionFilter {
+    pub const ALL: u32 = u32::MAX;
+    pub const NONE: u32 = 0;
+
+    pub fn new(layers: u32, mask: u32) -> Self {
+        Self { layers, mask }
+    }
+
+    pub fn collides_with(&self, other: &CollisionFilter) -> bool {
+        self.layers & other.mask != 0 && other.layers & self.mask != 0
+    }
+}
+
+impl Default for CollisionFilter {
+    fn default() -> Self {
+        Self {
+            layers: 1,
+            mask: Self::ALL,
+        }
+    }
+}
+
+/// Which sides of a block stop balls. A ball crossing a non-solid side enters
+/// the block and leaves through any side, solid or not, from the inside.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockEdges {
+    pub top: bool,
+    pub left: bool,
+    pub bottom: bool,
+    pub right: bool,
+}
+
+impl BlockEdges {
+    pub const ALL: Self = Self {
+        top: true,
+        left: true,
+        bottom: true,
+        right: true,
+    };
+    pub const NONE: Self = Self {
+        top: false,
+        left: false,
+        bottom: false,
+        right: false,
+    };
+}
+
+impl Default for BlockEdges {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// Parts of a block's outline covered by touching neighbours, as computed by
+/// [`SimulationState::merge_block_edges`]. Collision detection skips them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockSeams {
+    pub top: bool,
+    pub left: bool,
+    pub bottom: bool,
+    pub right: bool,
+    pub top_left: bool,
+    pub top_right: bool,
+    pub bottom_left: bool,
+    pub bottom_right: bool,
+}
+
+impl BlockSeams {
+    pub const NONE: Self = Self {
+        top: false,
+        left: false,
+        bottom: false,
+        right: false,
+        top_left: false,
+        top_right: false,
+        bottom_left: false,
+        bottom_right: false,
+    };
+}
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ball {
     pub position: Vector2<f64>,
     pub velocity: Vector2<f64>,
     pub radius: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub filter: CollisionFilter,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Block {
     pub max_y: f64,
     pub min_x: f64,
     pub max_x: f64,
     pub min_y: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub filter: CollisionFilter,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub solid: BlockEdges,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seams: BlockSeams,
 }
 
 impl Block {
@@ -28,17 +154,195 @@ impl Block {
             min_y: bottom,
             min_x: left,
             max_x: right,
+            filter: CollisionFilter::default(),
+            solid: BlockEdges::ALL,
+            seams: BlockSeams::NONE,
+        }
+    }
+
+    /// Square block reaching `half_size` out from the centre of column `x` and
+    /// row `y` of a `columns` x `rows` grid spread evenly over the unit square,
+    /// rows counting up from the bottom. This is the layout the game uses.
+    pub fn grid_cell(columns: usize, rows: usize, x: usize, y: usize, half_size: f64) -> Self {
+        let y = (1.0 + y as f64) / (rows as f64 + 1.0);
+        let x = (1.0 + x as f64) / (columns as f64 + 1.0);
+        Self::new(y + half_size, y - half_size, x - half_size, x + half_size)
+    }
+}
+
+/// Copy-on-write list of blocks. Clones share the same list until one of them
+/// is changed, which makes copying a [`SimulationState`] cheap.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Vec<Block>", into = "Vec<Block>")
+)]
+pub struct Blocks(Arc<Vec<Block>>);
+
+impl Blocks {
+    /// Whether both lists are still the same copy.
+    pub fn is_shared_with(&self, other: &Blocks) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for Blocks {
+    type Target = Vec<Block>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Blocks {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+impl From<Vec<Block>> for Blocks {
+    fn from(blocks: Vec<Block>) -> Self {
+        Self(Arc::new(blocks))
+    }
+}
+
+impl From<Blocks> for Vec<Block> {
+    fn from(blocks: Blocks) -> Self {
+        Arc::try_unwrap(blocks.0).unwrap_or_else(|blocks| (*blocks).clone())
+    }
+}
+
+impl FromIterator<Block> for Blocks {
+    fn from_iter<T: IntoIterator<Item = Block>>(iter: T) -> Self {
+        Self(Arc::new(iter.into_iter().collect()))
+    }
+}
+
+impl<'a> IntoIterator for &'a Blocks {
+    type Item = &'a Block;
+    type IntoIter = std::slice::Iter<'a, Block>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Non-solid region that reports balls entering and leaving it without
+/// changing their velocity.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sensor {
+    pub max_y: f64,
+    pub min_x: f64,
+    pub max_x: f64,
+    pub min_y: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub filter: CollisionFilter,
+}
+
+impl Sensor {
+    pub fn new(top: f64, bottom: f64, left: f64, right: f64) -> Self {
+        Self {
+            max_y: top,
+            min_y: bottom,
+            min_x: left,
+            max_x: right,
+            filter: CollisionFilter::default(),
+        }
+    }
+}
+
+/// Play area bounded by an arbitrary, possibly concave, polygon with polygonal
+/// holes. Vertices can be given in either winding order.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Arena {
+    pub boundary: Vec<Vector2<f64>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub holes: Vec<Vec<Vector2<f64>>>,
+}
+
+impl Arena {
+    pub fn new(boundary: Vec<Vector2<f64>>) -> Self {
+        Self {
+            boundary,
+            holes: vec![],
+        }
+    }
+
+    pub fn rectangle(width: f64, height: f64) -> Self {
+        Self::new(vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(width, 0.0),
+            Vector2::new(width, height),
+            Vector2::new(0.0, height),
+        ])
+    }
+
+    pub fn with_hole(mut self, hole: Vec<Vector2<f64>>) -> Self {
+        self.holes.push(hole);
+        self
+    }
+}
+
+/// How `SimulationState::next` looks for the earliest event. `Parallel` spreads
+/// the per-ball search across threads with the `rayon` feature and is the same
+/// as `Serial` without it. Both always find the same event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EventSearch {
+    Serial,
+    Parallel,
+}
+
+impl Default for EventSearch {
+    fn default() -> Self {
+        if cfg!(feature = "rayon") {
+            Self::Parallel
+        } else {
+            Self::Serial
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct SimulationState {
     pub time: f64,
     pub space_width: f64,
     pub space_height: f64,
     pub balls: Vec<Ball>,
-    pub blocks: Vec<Block>,
+    pub blocks: Blocks,
+    pub sensors: Vec<Sensor>,
+    /// Replaces the walls of the `space_width` x `space_height` rectangle.
+    pub arena: Option<Arena>,
+    pub search: EventSearch,
+    /// Off unless set, see [`StallDetection`].
+    pub stall_detection: Option<StallDetection>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub stall_tracker: StallTracker,
+}
+
+impl Default for SimulationState {
+    fn default() -> Self {
+        Self {
+            time: 0.0,
+            space_width: 1.0,
+            space_height: 1.0,
+            balls: vec![],
+            blocks: Blocks::default(),
+            sensors: vec![],
+            arena: None,
+            search: EventSearch::default(),
+            stall_detection: None,
+            stall_tracker: StallTracker::default(),
+        }
+    }
 }
 
 impl SimulationState {
@@ -50,39 +354,43 @@ impl SimulationState {
     }
 
     pub fn next(
-        mut self,
+        self,
         custom_events: &[Event<EventType>],
     ) -> Option<(SimulationState, Event<EventType>)> {
-        self.earliest_event(custom_events).map(|event| {
-            let Event {
-                time,
-                data: event_data,
-            } = event;
+        self.next_with(custom_events, &mut ())
+    }
 
-            self.forward(time);
+    pub fn next_with(
+        self,
+        custom_events: &[Event<EventType>],
+        handler: &mut impl CollisionHandler,
+    ) -> Option<(SimulationState, Event<EventType>)> {
+        self.advance(custom_events, handler, |_, _, _| {})
+    }
+
+    // `inspect` sees the final response right before it is applied
+    fn advance(
+        mut self,
+        custom_events: &[Event<EventType>],
+        handler: &mut impl CollisionHandler,
+        inspect: impl FnOnce(&SimulationState, &Event<EventType>, &CollisionResponse),
+    ) -> Option<(SimulationState, Event<EventType>)> {
+        let event = match self.pending_stall() {
+            Some(stall) => Some(stall),
+            None => self.earliest_event(custom_events),
+        };
+        event.map(|event| {
+            self.forward(event.time);
 
             //println!("{:?}", event);
 
-            match event_data {
-                EventType::Collision(CollisionData { ball, against }) => match against {
-                    CollisionType::Wall(wall_type) => match wall_type {
-                        WallType::YPositive | WallType::YNegative => {
-                            self.balls[ball].velocity.y *= -1.0
-                        }
-                        WallType::XNegative | WallType::XPositive => {
-                            self.balls[ball].velocity.x *= -1.0
-                        }
-                    },
-                    CollisionType::Block {
-                        contact_position, ..
-                    } => {
-                        let ball = &mut self.balls[ball];
-                        let contact_normal = (contact_position - ball.position).normalize();
-                        let normal_velocity = ball.velocity.dot(&contact_normal) * contact_normal;
-                        ball.velocity -= 2.0 * normal_velocity;
-                    }
-                },
-                EventType::Custom => {}
+            let mut response = self.default_response(&event);
+            handler.handle(&self, &event, &mut response);
+            inspect(&self, &event, &response);
+            let removed = response.remove_ball;
+            self.apply_response(&event, response);
+            if !removed {
+                self.detect_stall(&event);
             }
 
             (self, event)
@@ -97,23 +405,53 @@ mod svg_mod {
         node::element::{path::Data, Circle, Group, Path, Rectangle},
         Document,
     };
+
+    /// Document units per simulation unit in pages written by `save_img`.
+    pub const SVG_SCALE: f64 = 10.0;
+    // Length of the velocity line drawn from each ball per unit of speed
+    pub(crate) const VELOCITY_LINE_SCALE: f64 = 0.02;
+
     impl SimulationState {
         pub fn save_img(&self, document: Document, page: usize) -> Document {
             let mut group = Group::new().set("inkscape:groupmode", "layer").set(
                 "transform",
-                format!("matrix(10, 0, 0, -10, {}, 10)", page * 11),
+                format!(
+                    "matrix({SVG_SCALE}, 0, 0, -{SVG_SCALE}, {}, {SVG_SCALE})",
+                    page as f64 * (SVG_SCALE + 1.0)
+                ),
             );
 
-            group = group.add(
-                Rectangle::new()
-                    .set("fill", "none")
-                    .set("stroke", "black")
-                    .set("stroke-width", 0.001)
-                    .set("x", 0)
-                    .set("y", 0)
-                    .set("width", 1)
-                    .set("height", 1),
-            );
+            group = if let Some(arena) = &self.arena {
+                let mut data = Data::new();
+                for polygon in std::iter::once(&arena.boundary).chain(&arena.holes) {
+                    for (i, point) in polygon.iter().enumerate() {
+                        data = if i == 0 {
+                            data.move_to((point.x, point.y))
+                        } else {
+                            data.line_to((point.x, point.y))
+                        };
+                    }
+                    data = data.close();
+                }
+                group.add(
+                    Path::new()
+                        .set("fill", "none")
+                        .set("stroke", "black")
+                        .set("stroke-width", 0.001)
+                        .set("d", data),
+                )
+            } else {
+                group.add(
+                    Rectangle::new()
+                        .set("fill", "none")
+                        .set("stroke", "black")
+                        .set("stroke-width", 0.001)
+                        .set("x", 0)
+                        .set("y", 0)
+                        .set("width", 1)
+                        .set("height", 1),
+                )
+            };
 
             for ball in &self.balls {
                 group = group
@@ -134,7 +472,10 @@ mod svg_mod {
                                 "d",
                                 Data::new()
                                     .move_to((ball.position.x, ball.position.y))
-                                    .line_by((ball.velocity.x * 0.02, ball.velocity.y * 0.02)),
+                                    .line_by((
+                                        ball.velocity.x * VELOCITY_LINE_SCALE,
+                                        ball.velocity.y * VELOCITY_LINE_SCALE,
+                                    )),
                             ),
                     );
             }
@@ -160,24 +501,30 @@ mod svg_mod {
 pub use svg_mod::*;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Event<T> {
     pub time: f64,
     pub data: T,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EventType {
     Collision(CollisionData),
+    Sensor(SensorData),
+    Stalled(StallData),
     Custom,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CollisionData {
     pub ball: usize,
     pub against: CollisionType,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WallType {
     YPositive,
     YNegative,
@@ -186,10 +533,31 @@ pub enum WallType {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CollisionType {
     Wall(WallType),
     Block {
         index: usize,
         contact_position: Vector2<f64>,
     },
+    /// Edge of the arena, numbered along the boundary and then along each hole.
+    Arena {
+        edge: usize,
+        contact_position: Vector2<f64>,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SensorData {
+    pub ball: usize,
+    pub sensor: usize,
+    pub kind: SensorEventType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SensorEventType {
+    Enter,
+    Exit,
 }
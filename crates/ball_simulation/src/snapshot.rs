@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+use crate::SimulationState;
+
+/// Saved copy of a [`SimulationState`]. Blocks stay shared with the state it
+/// was taken from until one of them changes them.
+#[derive(Debug, Clone)]
+pub struct Snapshot(SimulationState);
+
+impl Snapshot {
+    pub fn state(&self) -> &SimulationState {
+        &self.0
+    }
+}
+
+impl SimulationState {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(self.clone())
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.clone_from(&snapshot.0);
+    }
+}
+
+/// Latest snapshots up to a fixed count, the oldest is dropped when a new one
+/// doesn't fit.
+#[derive(Debug, Clone)]
+pub struct History {
+    snapshots: VecDeque<Snapshot>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    pub fn record(&mut self, state: &SimulationState) {
+        self.push(state.snapshot());
+    }
+
+    /// Snapshot taken `age` pushes ago, 0 being the latest.
+    pub fn get(&self, age: usize) -> Option<&Snapshot> {
+        self.snapshots.iter().rev().nth(age)
+    }
+
+    pub fn pop(&mut self) -> Option<Snapshot> {
+        self.snapshots.pop_back()
+    }
+
+    /// Restores the latest snapshot into `state` and forgets it. Returns false
+    /// when there is nothing to undo.
+    pub fn undo(&mut self, state: &mut SimulationState) -> bool {
+        match self.pop() {
+            Some(Snapshot(snapshot)) => {
+                *state = snapshot;
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use nalgebra::{Rotation2, Vector2};
+
+use crate::{Ball, CollisionData, Event, EventType, SensorData, SimulationState};
+
+// Largest difference between two ball states still counted as a repeat
+const PERIOD_TOLERANCE: f64 = 1e-9;
+
+/// What happens to a stalled ball when the handler leaves the response as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StallPolicy {
+    /// Turns the velocity to `min_angle` from the horizontal when it is
+    /// flatter than that, and otherwise rotates it by `min_angle`.
+    Nudge,
+    RemoveBall,
+    /// Only emits the `Stalled` event.
+    Report,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StallReason {
+    /// Left a collision closer to the horizontal than `min_angle`.
+    NearHorizontal,
+    /// Left a collision with the same position and velocity as a recent one.
+    Periodic,
+    /// More than `max_events` events happened within `rate_window`.
+    EventRate,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StallData {
+    pub ball: usize,
+    pub reason: StallReason,
+}
+
+/// Settings for noticing balls that would take extremely long, or forever,
+/// to come back. A stalled ball gets an `EventType::Stalled` event right
+/// after the event that revealed it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StallDetection {
+    pub policy: StallPolicy,
+    /// Smallest angle from the horizontal, in radians.
+    pub min_angle: f64,
+    pub max_events: usize,
+    pub rate_window: f64,
+    /// Number of recent events searched for a repeated ball state.
+    pub period_window: usize,
+}
+
+impl Default for StallDetection {
+    fn default() -> Self {
+        Self {
+            policy: StallPolicy::Nudge,
+            min_angle: 0.05,
+            max_events: 1000,
+            rate_window: 0.01,
+            period_window: 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Collision {
+    ball: usize,
+    position: Vector2<f64>,
+    velocity: Vector2<f64>,
+    balls: usize,
+    blocks: usize,
+}
+
+/// Recent events seen by stall detection.
+#[derive(Debug, Clone, Default)]
+pub struct StallTracker {
+    event_times: VecDeque<f64>,
+    collisions: VecDeque<Collision>,
+    // Stalled ball as it was when found, to notice if it was changed since
+    pending: Option<(StallData, Ball)>,
+}
+
+impl StallTracker {
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+pub(crate) fn nudge(velocity: Vector2<f64>, reason: StallReason, min_angle: f64) -> Vector2<f64> {
+    match reason {
+        StallReason::NearHorizontal => {
+            // Flat balls are sent down, towards the floor
+            Vector2::new(velocity.x.signum() * min_angle.cos(), -min_angle.sin()) * velocity.norm()
+        }
+        StallReason::Periodic | StallReason::EventRate => Rotation2::new(min_angle) * velocity,
+    }
+}
+
+impl SimulationState {
+    pub(crate) fn pending_stall(&mut self) -> Option<Event<EventType>> {
+        let (stall, ball) = self.stall_tracker.pending.take()?;
+        let current = self.balls.get(stall.ball)?;
+        (current.position == ball.position && current.velocity == ball.velocity).then_some(Event {
+            time: 0.0,
+            data: EventType::Stalled(stall),
+        })
+    }
+
+    pub(crate) fn detect_stall(&mut self, event: &Event<EventType>) {
+        let Some(settings) = self.stall_detection else {
+            return;
+        };
+        let (ball, collision) = match event.data {
+            EventType::Collision(CollisionData { ball, .. }) => (ball, true),
+            EventType::Sensor(SensorData { ball, .. }) => (ball, false),
+            EventType::Stalled(_) | EventType::Custom => return,
+        };
+        let Some(&current) = self.balls.get(ball) else {
+            return;
+        };
+        let tracker = &mut self.stall_tracker;
+
+        tracker.event_times.push_back(self.time);
+        while tracker
+            .event_times
+            .front()
+            .is_some_and(|&time| time < self.time - settings.rate_window)
+        {
+            tracker.event_times.pop_front();
+        }
+
+        let reason = if tracker.event_times.len() > settings.max_events {
+            tracker.event_times.clear();
+            Some(StallReason::EventRate)
+        } else if !collision {
+            None
+        } else if current.velocity.y.abs() < current.velocity.norm() * settings.min_angle.sin() {
+            Some(StallReason::NearHorizontal)
+        } else {
+            let collision = Collision {
+                ball,
+                position: current.position,
+                velocity: current.velocity,
+                balls: self.balls.len(),
+                blocks: self.blocks.len(),
+            };
+            let repeated = tracker.collisions.iter().any(|other| {
+                other.ball == collision.ball
+                    && other.balls == collision.balls
+                    && other.blocks == collision.blocks
+                    && (other.position - collision.position).norm() < PERIOD_TOLERANCE
+                    && (other.velocity - collision.velocity).norm() < PERIOD_TOLERANCE
+            });
+            if repeated {
+                tracker.collisions.clear();
+                Some(StallReason::Periodic)
+            } else {
+                tracker.collisions.push_back(collision);
+                if tracker.collisions.len() > settings.period_window {
+                    tracker.collisions.pop_front();
+                }
+                None
+            }
+        };
+
+        tracker.pending = reason.map(|reason| (StallData { ball, reason }, current));
+    }
+}
@@ -0,0 +1,96 @@
+use nalgebra::Vector2;
+
+use crate::{Block, BlockSeams, SimulationState};
+
+// How far apart two faces can be and still count as touching
+const SEAM_TOLERANCE: f64 = 1e-6;
+
+impl SimulationState {
+    /// Marks the edges and corners of blocks that are covered by touching
+    /// neighbours, so balls sliding over a row or column of blocks see one
+    /// flat surface instead of clipping the internal corners. Only blocks with
+    /// the same collision filter are merged. Run it again after adding or
+    /// removing blocks.
+    ///
+    /// Only layouts where blocks actually touch are affected: the game's grid
+    /// ([`Block::grid_cell`] with a half size of 0.04) leaves gaps between
+    /// cells, so it stays as it is.
+    pub fn merge_block_edges(&mut self) {
+        let seams = self
+            .blocks
+            .iter()
+            .map(|block| block_seams(block, &self.blocks))
+            .collect::<Vec<_>>();
+
+        for (block, seams) in self.blocks.iter_mut().zip(seams) {
+            block.seams = seams;
+        }
+    }
+}
+
+fn block_seams(block: &Block, blocks: &[Block]) -> BlockSeams {
+    let neighbours = blocks
+        .iter()
+        .filter(|other| other.filter == block.filter)
+        .collect::<Vec<_>>();
+
+    let covered = |line: f64, from: f64, to: f64, face: fn(&Block) -> (f64, f64, f64)| {
+        let mut intervals = neighbours
+            .iter()
+            .map(|other| face(other))
+            .filter(|&(other_line, ..)| (other_line - line).abs() <= SEAM_TOLERANCE)
+            .map(|(_, start, end)| (start, end))
+            .collect::<Vec<_>>();
+        intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut reached = from + SEAM_TOLERANCE;
+        for (start, end) in intervals {
+            if start > reached {
+                break;
+            }
+            reached = reached.max(end + SEAM_TOLERANCE);
+        }
+        reached >= to
+    };
+
+    // A corner of the merged outline has exactly one occupied quadrant around
+    // it, or two diagonally opposite ones.
+    let hidden_corner = |corner: Vector2<f64>| {
+        let [bottom_left, bottom_right, top_left, top_right] =
+            [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].map(|(x, y)| {
+                let probe = corner + Vector2::new(x, y) * SEAM_TOLERANCE * 2.0;
+                neighbours.iter().any(|other| {
+                    (other.min_x..=other.max_x).contains(&probe.x)
+                        && (other.min_y..=other.max_y).contains(&probe.y)
+                })
+            });
+        match [bottom_left, bottom_right, top_left, top_right]
+            .into_iter()
+            .filter(|&occupied| occupied)
+            .count()
+        {
+            0 | 1 => false,
+            2 => bottom_left == bottom_right || bottom_left == top_left,
+            _ => true,
+        }
+    };
+
+    BlockSeams {
+        top: covered(block.max_y, block.min_x, block.max_x, |other| {
+            (other.min_y, other.min_x, other.max_x)
+        }),
+        left: covered(block.min_x, block.min_y, block.max_y, |other| {
+            (other.max_x, other.min_y, other.max_y)
+        }),
+        bottom: covered(block.min_y, block.min_x, block.max_x, |other| {
+            (other.max_y, other.min_x, other.max_x)
+        }),
+        right: covered(block.max_x, block.min_y, block.max_y, |other| {
+            (other.min_x, other.min_y, other.max_y)
+        }),
+        top_left: hidden_corner(Vector2::new(block.min_x, block.max_y)),
+        top_right: hidden_corner(Vector2::new(block.max_x, block.max_y)),
+        bottom_left: hidden_corner(Vector2::new(block.min_x, block.min_y)),
+        bottom_right: hidden_corner(Vector2::new(block.max_x, block.min_y)),
+    }
+}
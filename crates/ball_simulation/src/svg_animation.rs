@@ -0,0 +1,390 @@
+use nalgebra::Vector2;
+use svg::{
+    node::element::{path::Data, Animate, Circle, Group, Path, Polyline, Rectangle},
+    Document,
+};
+
+use crate::{CollisionData, CollisionType, Event, EventType, SimulationState};
+
+/// How [`SimulationState::save_animation`] draws a run.
+#[derive(Debug, Clone)]
+pub struct AnimationOptions {
+    /// Document units per simulation unit.
+    pub scale: f64,
+    /// Playback seconds per unit of simulation time.
+    pub seconds_per_time: f64,
+    /// Restart the animation when it ends instead of freezing on the last frame.
+    pub repeat: bool,
+    /// Animate the balls and blocks. When false only the static drawing and
+    /// trajectories are written.
+    pub animate: bool,
+    /// Stroke colour for each ball's path, `None` to leave trajectories out.
+    pub trajectory_color: Option<String>,
+    pub ball_color: String,
+    pub block_color: String,
+    pub wall_color: String,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            scale: 100.0,
+            seconds_per_time: 1.0,
+            repeat: true,
+            animate: true,
+            trajectory_color: None,
+            ball_color: "#ff681d".to_string(),
+            block_color: "#1a5fb4".to_string(),
+            wall_color: "black".to_string(),
+        }
+    }
+}
+
+/// Path of one ball through a run. Keyframes are taken wherever its velocity
+/// changed, the ball moves in a straight line between them.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BallTrack {
+    pub radius: f64,
+    pub keyframes: Vec<(f64, Vector2<f64>)>,
+    pub velocity: Vector2<f64>,
+    pub removed: Option<f64>,
+}
+
+impl BallTrack {
+    fn position_at(&self, time: f64) -> Vector2<f64> {
+        let (last_time, last_position) = self.keyframes[self.keyframes.len() - 1];
+        last_position + self.velocity * (time - last_time)
+    }
+
+    fn spawned(&self) -> f64 {
+        self.keyframes[0].0
+    }
+}
+
+pub(crate) fn ball_tracks<'a>(
+    initial: &SimulationState,
+    steps: impl IntoIterator<Item = &'a (SimulationState, Event<EventType>)>,
+) -> (Vec<BallTrack>, Vec<Option<f64>>) {
+    let new_track = |ball: &crate::Ball, time: f64| BallTrack {
+        radius: ball.radius,
+        keyframes: vec![(time, ball.position)],
+        velocity: ball.velocity,
+        removed: None,
+    };
+
+    let mut tracks = initial
+        .balls
+        .iter()
+        .map(|ball| new_track(ball, initial.time))
+        .collect::<Vec<_>>();
+    let mut alive = (0..tracks.len()).collect::<Vec<_>>();
+    let mut block_removals = vec![None; initial.blocks.len()];
+    let mut alive_blocks = (0..initial.blocks.len()).collect::<Vec<_>>();
+
+    for (state, event) in steps {
+        let time = state.time;
+
+        let removed = state.removed_ball(event, |index| {
+            alive
+                .get(index)
+                .map(|&track| tracks[track].position_at(time))
+        });
+        if let Some(index) = removed {
+            let track = &mut tracks[alive.remove(index)];
+            let position = track.position_at(time);
+            track.keyframes.push((time, position));
+            track.removed = Some(time);
+        }
+
+        if let EventType::Collision(CollisionData {
+            against: CollisionType::Block { index, .. },
+            ..
+        }) = event.data
+        {
+            if state.blocks.len() < alive_blocks.len() && index < alive_blocks.len() {
+                block_removals[alive_blocks.remove(index)] = Some(time);
+            }
+        }
+
+        for (index, ball) in state.balls.iter().enumerate() {
+            match alive.get(index) {
+                Some(&track) => {
+                    let track = &mut tracks[track];
+                    if track.velocity != ball.velocity {
+                        track.keyframes.push((time, ball.position));
+                        track.velocity = ball.velocity;
+                    }
+                }
+                None => {
+                    alive.push(tracks.len());
+                    tracks.push(new_track(ball, time));
+                }
+            }
+        }
+    }
+
+    (tracks, block_removals)
+}
+
+// Values for a SMIL `animate` element spanning the whole run
+fn key_times(times: impl IntoIterator<Item = f64>, start: f64, duration: f64) -> String {
+    times
+        .into_iter()
+        .map(|time| ((time - start) / duration).clamp(0.0, 1.0).to_string())
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+impl SimulationState {
+    /// Draws a whole run as a single animated SVG. `steps` are the results of
+    /// successive `next` or `next_with` calls starting from `self`. Balls move
+    /// with SMIL animations, appear when spawned and vanish when removed, and
+    /// removed blocks disappear at the time they were hit.
+    pub fn save_animation<'a>(
+        &self,
+        steps: impl IntoIterator<Item = &'a (SimulationState, Event<EventType>)>,
+        options: &AnimationOptions,
+    ) -> Document {
+        let mut end = self.time;
+        let (tracks, block_removals) = ball_tracks(
+            self,
+            steps.into_iter().inspect(|(state, _)| end = state.time),
+        );
+        let start = self.time;
+        let duration = end - start;
+        let animate = options.animate && duration > 0.0;
+
+        let (min, max) = match &self.arena {
+            Some(arena) => arena.boundary.iter().fold(
+                (
+                    Vector2::repeat(f64::INFINITY),
+                    Vector2::repeat(f64::NEG_INFINITY),
+                ),
+                |(min, max), point| (min.inf(point), max.sup(point)),
+            ),
+            None => (
+                Vector2::zeros(),
+                Vector2::new(self.space_width, self.space_height),
+            ),
+        };
+        let size = (max - min) * options.scale;
+
+        let animation = |attribute: &str, values: String, key_times: String| {
+            Animate::new()
+                .set("attributeName", attribute)
+                .set("values", values)
+                .set("keyTimes", key_times)
+                .set("dur", format!("{}s", duration * options.seconds_per_time))
+                .set("fill", "freeze")
+                .set(
+                    "repeatCount",
+                    if options.repeat { "indefinite" } else { "1" },
+                )
+        };
+        let visibility = |from: f64, to: Option<f64>| {
+            let mut values = vec![];
+            let mut times = vec![];
+            if from > start {
+                values.push("hidden");
+                times.push(start);
+            }
+            values.push("visible");
+            times.push(from);
+            if let Some(to) = to {
+                values.push("hidden");
+                times.push(to);
+            }
+            animation(
+                "visibility",
+                values.join(";"),
+                key_times(times, start, duration),
+            )
+            .set("calcMode", "discrete")
+        };
+
+        let mut group = Group::new().set(
+            "transform",
+            format!(
+                "matrix({scale}, 0, 0, -{scale}, {}, {})",
+                -min.x * options.scale,
+                max.y * options.scale,
+                scale = options.scale,
+            ),
+        );
+
+        let stroke_width = 1.0 / options.scale;
+        group = if let Some(arena) = &self.arena {
+            let mut data = Data::new();
+            for polygon in std::iter::once(&arena.boundary).chain(&arena.holes) {
+                for (i, point) in polygon.iter().enumerate() {
+                    data = if i == 0 {
+                        data.move_to((point.x, point.y))
+                    } else {
+                        data.line_to((point.x, point.y))
+                    };
+                }
+                data = data.close();
+            }
+            group.add(
+                Path::new()
+                    .set("fill", "none")
+                    .set("stroke", options.wall_color.as_str())
+                    .set("stroke-width", stroke_width)
+                    .set("d", data),
+            )
+        } else {
+            group.add(
+                Rectangle::new()
+                    .set("fill", "none")
+                    .set("stroke", options.wall_color.as_str())
+                    .set("stroke-width", stroke_width)
+                    .set("x", 0)
+                    .set("y", 0)
+                    .set("width", self.space_width)
+                    .set("height", self.space_height),
+            )
+        };
+
+        for (i, (block, removed)) in self.blocks.iter().zip(block_removals).enumerate() {
+            let mut rectangle = Rectangle::new()
+                .set("fill", options.block_color.as_str())
+                .set("stroke", "none")
+                .set("x", block.min_x)
+                .set("y", block.min_y)
+                .set("width", block.max_x - block.min_x)
+                .set("height", block.max_y - block.min_y)
+                .set("id", format!("block{i}"));
+            if animate && removed.is_some() {
+                rectangle = rectangle.add(visibility(start, removed));
+            }
+            group = group.add(rectangle);
+        }
+
+        for (i, track) in tracks.iter().enumerate() {
+            let mut keyframes = track.keyframes.clone();
+            if track.removed.is_none() {
+                keyframes.push((end, track.position_at(end)));
+            }
+
+            if let Some(color) = &options.trajectory_color {
+                group = group.add(
+                    Polyline::new()
+                        .set("fill", "none")
+                        .set("stroke", color.as_str())
+                        .set("stroke-width", track.radius / 2.0)
+                        .set(
+                            "points",
+                            keyframes
+                                .iter()
+                                .map(|(_, position)| format!("{},{}", position.x, position.y))
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                        ),
+                );
+            }
+
+            let (_, first) = keyframes[0];
+            let mut circle = Circle::new()
+                .set("fill", options.ball_color.as_str())
+                .set("stroke", "none")
+                .set("r", track.radius)
+                .set("cx", first.x)
+                .set("cy", first.y)
+                .set("id", format!("ball{i}"));
+            if animate {
+                // Hold the spawn position until the ball appears
+                // and keep the removal position afterwards, linear key times
+                // have to cover the whole run
+                if track.spawned() > start {
+                    keyframes.insert(0, (start, first));
+                }
+                if track.removed.is_some() {
+                    let (_, last) = keyframes[keyframes.len() - 1];
+                    keyframes.push((end, last));
+                }
+                let times = key_times(keyframes.iter().map(|&(time, _)| time), start, duration);
+                let values = |coordinate: fn(&Vector2<f64>) -> f64| {
+                    keyframes
+                        .iter()
+                        .map(|(_, position)| coordinate(position).to_string())
+                        .collect::<Vec<_>>()
+                        .join(";")
+                };
+                circle = circle
+                    .add(animation("cx", values(|p| p.x), times.clone()))
+                    .add(animation("cy", values(|p| p.y), times));
+                if track.spawned() > start || track.removed.is_some() {
+                    circle = circle.add(visibility(track.spawned(), track.removed));
+                }
+            }
+            group = group.add(circle);
+        }
+
+        Document::new()
+            .set("width", size.x)
+            .set("height", size.y)
+            .set("viewBox", (0, 0, size.x, size.y))
+            .add(group)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use nalgebra::Vector2;
+
+    use crate::{
+        Ball, CollisionData, CollisionFilter, CollisionResponse, CollisionType, Event, EventType,
+        SimulationState, WallType,
+    };
+
+    use super::ball_tracks;
+
+    fn ball(x: f64, y: f64, vx: f64, vy: f64) -> Ball {
+        Ball {
+            position: Vector2::new(x, y),
+            velocity: Vector2::new(vx, vy),
+            radius: 0.1,
+            filter: CollisionFilter::default(),
+        }
+    }
+
+    #[test]
+    fn tracks_follow_balls_across_removals() {
+        let initial = SimulationState {
+            balls: vec![ball(0.5, 0.5, 0.0, -1.0), ball(0.2, 0.5, 0.0, 1.0)],
+            ..Default::default()
+        };
+
+        let mut steps = vec![];
+        let mut state = initial.clone();
+        let mut handler =
+            |_: &SimulationState, event: &Event<EventType>, response: &mut CollisionResponse| {
+                if let EventType::Collision(CollisionData {
+                    against: CollisionType::Wall(WallType::YNegative),
+                    ..
+                }) = event.data
+                {
+                    response.remove_ball = true;
+                    response.spawn.push(ball(0.8, 0.1, 0.0, 1.0));
+                }
+            };
+        for _ in 0..2 {
+            let (next_state, event) = state.next_with(&[], &mut handler).unwrap();
+            state = next_state.clone();
+            steps.push((next_state, event));
+        }
+
+        let (tracks, _) = ball_tracks(&initial, &steps);
+        assert_eq!(tracks.len(), 3);
+
+        // Hits the floor at 0.4 and is replaced
+        assert_relative_eq!(tracks[0].removed.unwrap(), 0.4);
+        assert_relative_eq!(tracks[0].keyframes[1].1, Vector2::new(0.5, 0.1));
+        // Bounces off the ceiling at the same time after moving to index 0
+        assert_eq!(tracks[1].removed, None);
+        assert_eq!(tracks[1].velocity, Vector2::new(0.0, -1.0));
+        assert_relative_eq!(tracks[1].keyframes[1].1, Vector2::new(0.2, 0.9));
+        assert_relative_eq!(tracks[2].keyframes[0].0, 0.4);
+    }
+}
@@ -0,0 +1,326 @@
+use std::{collections::HashMap, fmt};
+
+use nalgebra::{Matrix3, Vector2, Vector3};
+use svg::{
+    node::element::{
+        path::{Command, Data, Position},
+        tag::Type,
+    },
+    parser::Event,
+};
+
+use crate::{svg_mod::VELOCITY_LINE_SCALE, Ball, Block, CollisionFilter, SimulationState};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SvgImportError {
+    /// The document is not valid SVG.
+    Parse(String),
+    /// There is no unfilled frame rectangle and the root has no `viewBox`.
+    MissingFrame,
+    InvalidAttribute {
+        element: String,
+        attribute: String,
+    },
+}
+
+impl fmt::Display for SvgImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(message) => write!(f, "invalid svg: {message}"),
+            Self::MissingFrame => write!(f, "no frame rectangle or viewBox to place the scene in"),
+            Self::InvalidAttribute { element, attribute } => {
+                write!(f, "invalid `{attribute}` on <{element}>")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SvgImportError {}
+
+type Attributes = HashMap<String, svg::node::Value>;
+
+struct Element {
+    name: String,
+    attributes: Attributes,
+    // Document space transform of the element's own coordinates
+    transform: Matrix3<f64>,
+}
+
+impl Element {
+    fn invalid(&self, attribute: &str) -> SvgImportError {
+        SvgImportError::InvalidAttribute {
+            element: self.name.clone(),
+            attribute: attribute.to_string(),
+        }
+    }
+
+    fn number(&self, attribute: &str) -> Result<f64, SvgImportError> {
+        match self.attributes.get(attribute) {
+            None => Ok(0.0),
+            Some(value) => value
+                .trim()
+                .trim_end_matches("px")
+                .parse()
+                .map_err(|_| self.invalid(attribute)),
+        }
+    }
+
+    fn point(&self, x: &str, y: &str) -> Result<Vector2<f64>, SvgImportError> {
+        Ok(apply(
+            &self.transform,
+            Vector2::new(self.number(x)?, self.number(y)?),
+        ))
+    }
+
+    // Inkscape writes presentation attributes into `style`
+    fn unfilled(&self) -> bool {
+        self.attributes.get("fill").map(|fill| fill.trim()) == Some("none")
+            || self.attributes.get("style").is_some_and(|style| {
+                style.split(';').any(|declaration| {
+                    declaration
+                        .split_once(':')
+                        .map(|(property, value)| (property.trim(), value.trim()))
+                        == Some(("fill", "none"))
+                })
+            })
+    }
+
+    // Start and end of a straight line, or of the first subpath of a path
+    fn line(&self) -> Result<Option<[Vector2<f64>; 2]>, SvgImportError> {
+        let local = match self.name.as_str() {
+            "line" => Some([
+                Vector2::new(self.number("x1")?, self.number("y1")?),
+                Vector2::new(self.number("x2")?, self.number("y2")?),
+            ]),
+            "path" => {
+                let Some(d) = self.attributes.get("d") else {
+                    return Ok(None);
+                };
+                let data = Data::parse(d).map_err(|_| self.invalid("d"))?;
+                path_line(&data)
+            }
+            _ => None,
+        };
+        Ok(local.map(|points| points.map(|point| apply(&self.transform, point))))
+    }
+}
+
+fn path_line(data: &Data) -> Option<[Vector2<f64>; 2]> {
+    let mut points: Vec<Vector2<f64>> = vec![];
+    for command in data.iter() {
+        let (position, parameters, axes): (_, _, &[usize]) = match command {
+            Command::Move(position, parameters) if points.is_empty() => {
+                (position, parameters, &[0, 1])
+            }
+            Command::Move(..) | Command::Close => break,
+            Command::Line(position, parameters) => (position, parameters, &[0, 1]),
+            Command::HorizontalLine(position, parameters) => (position, parameters, &[0]),
+            Command::VerticalLine(position, parameters) => (position, parameters, &[1]),
+            _ => return None,
+        };
+
+        for values in parameters.chunks_exact(axes.len()) {
+            let mut point = points.last().copied().unwrap_or_else(Vector2::zeros);
+            for (&axis, &value) in axes.iter().zip(values) {
+                match position {
+                    Position::Absolute => point[axis] = value as f64,
+                    Position::Relative => point[axis] += value as f64,
+                }
+            }
+            points.push(point);
+        }
+    }
+
+    match points[..] {
+        [start, .., end] if start != end => Some([start, end]),
+        _ => None,
+    }
+}
+
+fn apply(transform: &Matrix3<f64>, point: Vector2<f64>) -> Vector2<f64> {
+    (transform * Vector3::new(point.x, point.y, 1.0)).xy()
+}
+
+fn parse_transform(transform: &str) -> Option<Matrix3<f64>> {
+    let mut result = Matrix3::identity();
+    for function in transform.split_terminator(')') {
+        let (name, arguments) = function.split_once('(')?;
+        let arguments = arguments
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|argument| !argument.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<f64>, _>>()
+            .ok()?;
+
+        let matrix = match (name.trim().trim_start_matches(','), &arguments[..]) {
+            ("matrix", &[a, b, c, d, e, f]) => Matrix3::new(a, c, e, b, d, f, 0.0, 0.0, 1.0),
+            ("translate", &[x]) => Matrix3::new_translation(&Vector2::new(x, 0.0)),
+            ("translate", &[x, y]) => Matrix3::new_translation(&Vector2::new(x, y)),
+            ("scale", &[s]) => Matrix3::new_scaling(s),
+            ("scale", &[x, y]) => Matrix3::new_nonuniform_scaling(&Vector2::new(x, y)),
+            ("rotate", &[angle]) => Matrix3::new_rotation(angle.to_radians()),
+            ("rotate", &[angle, x, y]) => {
+                let center = Vector2::new(x, y);
+                Matrix3::new_translation(&center)
+                    * Matrix3::new_rotation(angle.to_radians())
+                    * Matrix3::new_translation(&-center)
+            }
+            _ => return None,
+        };
+        result *= matrix;
+    }
+    Some(result)
+}
+
+impl SimulationState {
+    /// Builds a scene from an SVG drawing such as one sketched in Inkscape or
+    /// written by [`SimulationState::save_img`]. Rectangles become blocks,
+    /// circles become still balls, and a line or path starting inside a
+    /// circle gives that ball a velocity, drawn at the same length as
+    /// `save_img` draws it.
+    ///
+    /// The first unfilled rectangle is the frame: its bottom left corner is
+    /// the origin, its size sets `space_width` and `space_height`, and shapes
+    /// centred outside it are skipped. Without one the root `viewBox` is used.
+    /// `scale` is the number of document units per simulation unit,
+    /// [`SVG_SCALE`](crate::SVG_SCALE) for files written by `save_img`.
+    pub fn from_svg(content: &str, scale: f64) -> Result<Self, SvgImportError> {
+        let mut transforms = vec![Matrix3::identity()];
+        let mut view_box = None;
+        let mut elements = vec![];
+
+        for event in svg::read(content).map_err(|error| SvgImportError::Parse(error.to_string()))? {
+            let (name, kind, attributes) = match event {
+                Event::Tag(name, kind, attributes) => (name, kind, attributes),
+                Event::Error(error) => return Err(SvgImportError::Parse(error.to_string())),
+                _ => continue,
+            };
+            if kind == Type::End {
+                transforms.pop();
+                continue;
+            }
+
+            let parent = transforms[transforms.len() - 1];
+            let element = Element {
+                name: name.to_string(),
+                transform: match attributes.get("transform") {
+                    Some(transform) => {
+                        let invalid = SvgImportError::InvalidAttribute {
+                            element: name.to_string(),
+                            attribute: "transform".to_string(),
+                        };
+                        parent * parse_transform(transform).ok_or(invalid)?
+                    }
+                    None => parent,
+                },
+                attributes,
+            };
+            if kind == Type::Start {
+                transforms.push(element.transform);
+            }
+
+            if name == "svg" && view_box.is_none() {
+                view_box = element.attributes.get("viewBox").map(|value| {
+                    value
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter_map(|number| number.parse::<f64>().ok())
+                        .collect::<Vec<_>>()
+                });
+            }
+            elements.push(element);
+        }
+
+        let rectangle = |element: &Element| -> Result<[Vector2<f64>; 2], SvgImportError> {
+            let corner = Vector2::new(element.number("x")?, element.number("y")?);
+            let size = Vector2::new(element.number("width")?, element.number("height")?);
+            let corners = [
+                corner,
+                corner + Vector2::new(size.x, 0.0),
+                corner + Vector2::new(0.0, size.y),
+                corner + size,
+            ]
+            .map(|point| apply(&element.transform, point));
+            Ok([
+                corners.iter().fold(corners[0], |min, point| min.inf(point)),
+                corners.iter().fold(corners[0], |max, point| max.sup(point)),
+            ])
+        };
+
+        let frame = match elements
+            .iter()
+            .find(|element| element.name == "rect" && element.unfilled())
+        {
+            Some(element) => rectangle(element)?,
+            None => match view_box.as_deref() {
+                Some(&[x, y, width, height]) => {
+                    [Vector2::new(x, y), Vector2::new(x + width, y + height)]
+                }
+                _ => return Err(SvgImportError::MissingFrame),
+            },
+        };
+        let inside = |point: Vector2<f64>| {
+            (frame[0].x..=frame[1].x).contains(&point.x)
+                && (frame[0].y..=frame[1].y).contains(&point.y)
+        };
+        // Document y grows downwards
+        let to_scene =
+            |point: Vector2<f64>| Vector2::new(point.x - frame[0].x, frame[1].y - point.y) / scale;
+
+        let mut state = SimulationState {
+            space_width: (frame[1].x - frame[0].x) / scale,
+            space_height: (frame[1].y - frame[0].y) / scale,
+            ..Default::default()
+        };
+        let mut lines = vec![];
+
+        for element in &elements {
+            match element.name.as_str() {
+                "rect" if !element.unfilled() => {
+                    let [min, max] = rectangle(element)?;
+                    if inside((min + max) / 2.0) {
+                        let [top_left, bottom_right] = [to_scene(min), to_scene(max)];
+                        state.blocks.push(Block::new(
+                            top_left.y,
+                            bottom_right.y,
+                            top_left.x,
+                            bottom_right.x,
+                        ));
+                    }
+                }
+                "circle" => {
+                    let center = element.point("cx", "cy")?;
+                    if inside(center) {
+                        let stretch = element
+                            .transform
+                            .fixed_view::<2, 2>(0, 0)
+                            .determinant()
+                            .abs()
+                            .sqrt();
+                        let radius = element.number("r")? * stretch;
+                        state.balls.push(Ball {
+                            position: to_scene(center),
+                            velocity: Vector2::zeros(),
+                            radius: radius / scale,
+                            filter: CollisionFilter::default(),
+                        });
+                    }
+                }
+                "line" | "path" => lines.extend(element.line()?),
+                _ => {}
+            }
+        }
+
+        for [start, end] in lines {
+            let [start, end] = [to_scene(start), to_scene(end)];
+            if let Some(ball) = state
+                .balls
+                .iter_mut()
+                .find(|ball| (ball.position - start).norm() <= ball.radius)
+            {
+                ball.velocity = (end - start) / VELOCITY_LINE_SCALE;
+            }
+        }
+
+        Ok(state)
+    }
+}
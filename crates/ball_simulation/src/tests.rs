@@ -1,8 +1,14 @@
 use std::{assert_matches::assert_matches, f64::consts::PI};
 
+use approx::assert_relative_eq;
 use nalgebra::Vector2;
 
-use crate::{Ball, Block, Event, EventType, SimulationState};
+use crate::{
+    Arena, Ball, Block, BlockEdges, BlockSeams, CollisionData, CollisionFilter, CollisionHandler,
+    CollisionResponse, CollisionType, Emitter, Event, EventHistory, EventType, History, Sensor,
+    SensorData, SensorEventType, SimulationState, StallData, StallDetection, StallPolicy,
+    StallReason, WallType,
+};
 
 #[test]
 fn single_block_from_all_sides() {
@@ -15,8 +21,13 @@ fn single_block_from_all_sides() {
             max_x: 6.0,
             min_y: 4.0,
             max_y: 6.0,
-        }],
+            filter: CollisionFilter::default(),
+            solid: BlockEdges::ALL,
+            seams: BlockSeams::NONE,
+        }]
+        .into(),
         balls: vec![],
+        ..Default::default()
     };
 
     let add_ball_with_angle = |mut simulation_state: SimulationState, angle: f64| {
@@ -28,6 +39,7 @@ fn single_block_from_all_sides() {
             position,
             velocity,
             radius: 1.0,
+            filter: CollisionFilter::default(),
         }];
         simulation_state
     };
@@ -39,9 +51,9 @@ fn single_block_from_all_sides() {
             Some((
                 _,
                 Event {
-                    data: EventType::Collision(crate::CollisionData {
+                    data: EventType::Collision(CollisionData {
                         ball: 0,
-                        against: crate::CollisionType::Block { index: 0, .. },
+                        against: CollisionType::Block { index: 0, .. },
                     }),
                     ..
                 }
@@ -60,13 +72,19 @@ fn test_crash() {
             position: Vector2::new(0.15366112818873037, 0.4829665403837622),
             velocity: Vector2::new(-0.45658153289741865, -1.9471859962050475),
             radius: 0.02,
+            filter: CollisionFilter::default(),
         }],
         blocks: vec![Block {
             max_y: 0.4945454545454545,
             min_x: 0.05090909090909091,
             max_x: 0.13090909090909092,
             min_y: 0.41454545454545455,
-        }],
+            filter: CollisionFilter::default(),
+            solid: BlockEdges::ALL,
+            seams: BlockSeams::NONE,
+        }]
+        .into(),
+        ..Default::default()
     };
 
     simulation_state.next(&[]).unwrap().0.next(&[]).unwrap();
@@ -82,13 +100,19 @@ fn test_crash2() {
             position: Vector2::new(0.98, 0.45073156690401917),
             velocity: Vector2::new(-1.4579651981269763, 1.3690644546735433),
             radius: 0.02,
+            filter: CollisionFilter::default(),
         }],
         blocks: vec![Block {
             max_y: 0.9490909090909091,
             min_x: 0.5054545454545454,
             max_x: 0.5854545454545454,
             min_y: 0.869090909090909,
-        }],
+            filter: CollisionFilter::default(),
+            solid: BlockEdges::ALL,
+            seams: BlockSeams::NONE,
+        }]
+        .into(),
+        ..Default::default()
     };
 
     #[cfg(feature = "svg")]
@@ -128,13 +152,19 @@ fn test_close_bounce() {
             position: Vector2::new(0.98, 0.8400000576819083),
             velocity: Vector2::new(-0.9922778253475968, 1.736486313600958),
             radius: 0.02,
+            filter: CollisionFilter::default(),
         }],
         blocks: vec![Block {
             max_y: 0.9490909090909091,
             min_x: 0.869090909090909,
             max_x: 0.9490909090909091,
             min_y: 0.869090909090909,
-        }],
+            filter: CollisionFilter::default(),
+            solid: BlockEdges::ALL,
+            seams: BlockSeams::NONE,
+        }]
+        .into(),
+        ..Default::default()
     };
 
     use svg::Document;
@@ -151,3 +181,1017 @@ fn test_close_bounce() {
 
     svg::save("dbg.svg", &document).unwrap();
 }
+
+// Ball of radius 0.5 in an empty 10 by 10 space, for tests to add what it runs into
+fn single_ball(position: Vector2<f64>, velocity: Vector2<f64>) -> SimulationState {
+    SimulationState {
+        time: 0.0,
+        space_width: 10.0,
+        space_height: 10.0,
+        balls: vec![Ball {
+            position,
+            velocity,
+            radius: 0.5,
+            filter: CollisionFilter::default(),
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn collision_filters() {
+    let simulation_state = |ball_filter: CollisionFilter, block_filter: CollisionFilter| {
+        let mut state = SimulationState {
+            blocks: vec![Block {
+                filter: block_filter,
+                ..Block::new(6.0, 4.0, 4.0, 6.0)
+            }]
+            .into(),
+            ..single_ball(Vector2::new(5.0, 1.0), Vector2::new(0.0, 1.0))
+        };
+        state.balls[0].filter = ball_filter;
+        state
+    };
+
+    let hits_block = |ball_filter, block_filter| {
+        matches!(
+            simulation_state(ball_filter, block_filter).next(&[]),
+            Some((
+                _,
+                Event {
+                    data: EventType::Collision(CollisionData {
+                        against: CollisionType::Block { .. },
+                        ..
+                    }),
+                    ..
+                }
+            ))
+        )
+    };
+
+    assert!(hits_block(
+        CollisionFilter::default(),
+        CollisionFilter::default()
+    ));
+
+    // Ghost ball only hits walls.
+    let ghost = CollisionFilter::new(1, CollisionFilter::NONE);
+    assert!(!hits_block(ghost, CollisionFilter::default()));
+    assert_matches!(
+        simulation_state(ghost, CollisionFilter::default()).next(&[]),
+        Some((
+            _,
+            Event {
+                data: EventType::Collision(CollisionData {
+                    against: CollisionType::Wall(WallType::YPositive),
+                    ..
+                }),
+                ..
+            }
+        ))
+    );
+
+    // Glass block on layer 2 is ignored by balls that don't collide with it.
+    let glass = CollisionFilter::new(0b10, CollisionFilter::ALL);
+    assert!(!hits_block(CollisionFilter::new(0b01, 0b01), glass));
+    assert!(hits_block(CollisionFilter::new(0b01, 0b11), glass));
+
+    // Block that only stops balls of colour 0b100.
+    let picky = CollisionFilter::new(1, 0b100);
+    assert!(!hits_block(
+        CollisionFilter::new(0b010, CollisionFilter::ALL),
+        picky
+    ));
+    assert!(hits_block(
+        CollisionFilter::new(0b100, CollisionFilter::ALL),
+        picky
+    ));
+}
+
+#[test]
+fn one_way_block() {
+    let blocks = vec![Block {
+        solid: BlockEdges {
+            top: true,
+            ..BlockEdges::NONE
+        },
+        ..Block::new(6.0, 4.0, 4.0, 6.0)
+    }];
+    let simulation_state = |position, velocity| SimulationState {
+        blocks: blocks.clone().into(),
+        ..single_ball(position, velocity)
+    };
+
+    // From below the ball goes through the block, including while straddling its top edge.
+    for y in [1.0, 5.0, 5.9, 6.2] {
+        assert_matches!(
+            simulation_state(Vector2::new(5.0, y), Vector2::new(0.0, 1.0)).next(&[]),
+            Some((
+                _,
+                Event {
+                    data: EventType::Collision(CollisionData {
+                        against: CollisionType::Wall(WallType::YPositive),
+                        ..
+                    }),
+                    ..
+                }
+            ))
+        );
+    }
+
+    // From above it bounces off the top.
+    let (state, event) = simulation_state(Vector2::new(5.0, 9.0), Vector2::new(0.0, -1.0))
+        .next(&[])
+        .unwrap();
+    assert_matches!(
+        event.data,
+        EventType::Collision(CollisionData {
+            against: CollisionType::Block { index: 0, .. },
+            ..
+        })
+    );
+    assert_eq!(event.time, 2.5);
+    assert!(state.balls[0].velocity.y > 0.0);
+}
+
+#[test]
+fn ball_turning_back_inside_one_way_edge() {
+    let simulation_state = SimulationState {
+        blocks: vec![Block {
+            solid: BlockEdges {
+                top: true,
+                ..BlockEdges::NONE
+            },
+            ..Block::new(9.2, 8.0, 3.0, 7.0)
+        }]
+        .into(),
+        ..single_ball(Vector2::new(5.0, 5.0), Vector2::new(0.0, 1.0))
+    };
+
+    // Through the block from below and off the ceiling, close enough that the
+    // ball still straddles the top edge on its way back down.
+    let (state, event) = simulation_state.next(&[]).unwrap();
+    assert_matches!(
+        event.data,
+        EventType::Collision(CollisionData {
+            against: CollisionType::Wall(WallType::YPositive),
+            ..
+        })
+    );
+    assert_relative_eq!(state.balls[0].position.y, 9.5);
+
+    // It falls back through the block to the floor.
+    let (state, event) = state.next(&[]).unwrap();
+    assert_matches!(
+        event.data,
+        EventType::Collision(CollisionData {
+            against: CollisionType::Wall(WallType::YNegative),
+            ..
+        })
+    );
+    assert_relative_eq!(state.balls[0].position.y, 0.5);
+}
+
+#[test]
+fn sensor_enter_and_exit() {
+    let simulation_state = |position, velocity| SimulationState {
+        sensors: vec![Sensor::new(6.0, 4.0, 4.0, 6.0)],
+        ..single_ball(position, velocity)
+    };
+
+    let sensor_event = |event: Event<EventType>| match event.data {
+        EventType::Sensor(SensorData {
+            ball: 0,
+            sensor: 0,
+            kind,
+        }) => Some((event.time, kind)),
+        _ => None,
+    };
+
+    // Straight through the middle.
+    let velocity = Vector2::new(0.0, 1.0);
+    let (state, event) = simulation_state(Vector2::new(5.0, 1.0), velocity)
+        .next(&[])
+        .unwrap();
+    assert_eq!(sensor_event(event), Some((2.5, SensorEventType::Enter)));
+    assert_eq!(state.balls[0].velocity, velocity);
+    let (state, event) = state.next(&[]).unwrap();
+    assert_eq!(sensor_event(event), Some((3.0, SensorEventType::Exit)));
+    assert_eq!(state.balls[0].velocity, velocity);
+    assert_relative_eq!(state.time, 5.5);
+    let (_, event) = state.next(&[]).unwrap();
+    assert_matches!(
+        event.data,
+        EventType::Collision(CollisionData {
+            against: CollisionType::Wall(WallType::YPositive),
+            ..
+        })
+    );
+
+    // Clipping a corner diagonally.
+    let velocity = Vector2::new(1.0, 1.0);
+    let (state, event) = simulation_state(Vector2::new(2.0, 4.6), velocity)
+        .next(&[])
+        .unwrap();
+    assert_eq!(sensor_event(event).unwrap().1, SensorEventType::Enter);
+    let (state, event) = state.next(&[]).unwrap();
+    assert_eq!(sensor_event(event).unwrap().1, SensorEventType::Exit);
+    let distance = state.balls[0].position - Vector2::new(4.0, 6.0);
+    assert_relative_eq!(distance.magnitude(), 0.5, epsilon = 1e-9);
+    assert_eq!(state.balls[0].velocity, velocity);
+
+    // Starting inside only reports the exit.
+    let (_, event) = simulation_state(Vector2::new(5.0, 5.0), Vector2::new(-1.0, 0.0))
+        .next(&[])
+        .unwrap();
+    assert_eq!(sensor_event(event), Some((1.5, SensorEventType::Exit)));
+
+    // Filtered out balls are not reported.
+    let mut state = simulation_state(Vector2::new(5.0, 1.0), Vector2::new(0.0, 1.0));
+    state.sensors[0].filter = CollisionFilter::new(1, 0b10);
+    let (_, event) = state.next(&[]).unwrap();
+    assert_eq!(sensor_event(event), None);
+}
+
+#[test]
+fn collision_handlers() {
+    let simulation_state = SimulationState {
+        blocks: vec![Block::new(6.0, 4.0, 4.0, 6.0)].into(),
+        ..single_ball(Vector2::new(5.0, 1.0), Vector2::new(0.0, 1.0))
+    };
+
+    // Piercing ball keeps going and destroys the block.
+    let mut piercing =
+        |state: &SimulationState, event: &Event<EventType>, response: &mut CollisionResponse| {
+            if let EventType::Collision(CollisionData {
+                ball,
+                against: CollisionType::Block { .. },
+            }) = event.data
+            {
+                response.velocity = Some(state.balls[ball].velocity);
+                response.remove_block = true;
+            }
+        };
+    let (state, _) = simulation_state
+        .clone()
+        .next_with(&[], &mut piercing)
+        .unwrap();
+    assert!(state.blocks.is_empty());
+    assert_eq!(state.balls[0].velocity, Vector2::new(0.0, 1.0));
+
+    // Accelerator doubles the outgoing speed.
+    let mut accelerator =
+        |_: &SimulationState, _: &Event<EventType>, response: &mut CollisionResponse| {
+            response.velocity = response.velocity.map(|velocity| velocity * 2.0);
+        };
+    let (state, _) = simulation_state
+        .clone()
+        .next_with(&[], &mut accelerator)
+        .unwrap();
+    assert_eq!(state.balls[0].velocity, Vector2::new(0.0, -2.0));
+
+    // Splitting ball replaces itself with two diagonal balls.
+    struct Splitter(usize);
+    impl CollisionHandler for Splitter {
+        fn handle(
+            &mut self,
+            state: &SimulationState,
+            event: &Event<EventType>,
+            response: &mut CollisionResponse,
+        ) {
+            if let EventType::Collision(CollisionData { ball, .. }) = event.data {
+                self.0 += 1;
+                let ball = state.balls[ball];
+                let velocity = response.velocity.unwrap();
+                response.remove_ball = true;
+                response.spawn = [-1.0, 1.0]
+                    .into_iter()
+                    .map(|side| Ball {
+                        velocity: velocity + Vector2::new(side, 0.0),
+                        ..ball
+                    })
+                    .collect();
+            }
+        }
+    }
+    let mut splitter = Splitter(0);
+    let (state, _) = simulation_state.next_with(&[], &mut splitter).unwrap();
+    assert_eq!(splitter.0, 1);
+    assert_eq!(state.balls.len(), 2);
+    assert_eq!(state.blocks.len(), 1);
+    assert_eq!(state.balls[0].velocity, Vector2::new(-1.0, -1.0));
+    assert_eq!(state.balls[1].velocity, Vector2::new(1.0, -1.0));
+}
+
+#[test]
+fn merge_game_grid() {
+    let mut simulation_state = SimulationState {
+        blocks: (0..10)
+            .flat_map(|y| (0..10).map(move |x| Block::grid_cell(10, 10, x, y, 0.04)))
+            .collect(),
+        ..Default::default()
+    };
+
+    simulation_state.merge_block_edges();
+
+    assert!(simulation_state
+        .blocks
+        .iter()
+        .all(|block| block.seams == BlockSeams::NONE));
+}
+
+#[test]
+fn merge_touching_grid() {
+    let half_size = 0.5 / 11.0;
+    let mut simulation_state = SimulationState {
+        blocks: (0..2)
+            .flat_map(|y| (0..3).map(move |x| Block::grid_cell(10, 10, x, y, half_size)))
+            .chain([
+                // Only touches the top right block by a corner.
+                Block::grid_cell(10, 10, 3, 2, half_size),
+            ])
+            .collect(),
+        ..Default::default()
+    };
+
+    simulation_state.merge_block_edges();
+    let seams = simulation_state
+        .blocks
+        .iter()
+        .map(|block| block.seams)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        seams[0],
+        BlockSeams {
+            top: true,
+            right: true,
+            top_left: true,
+            top_right: true,
+            bottom_right: true,
+            ..BlockSeams::NONE
+        }
+    );
+    assert_eq!(
+        seams[1],
+        BlockSeams {
+            top: true,
+            left: true,
+            right: true,
+            top_left: true,
+            top_right: true,
+            bottom_left: true,
+            bottom_right: true,
+            ..BlockSeams::NONE
+        }
+    );
+    assert_eq!(
+        seams[5],
+        BlockSeams {
+            left: true,
+            bottom: true,
+            top_left: true,
+            bottom_left: true,
+            bottom_right: true,
+            ..BlockSeams::NONE
+        }
+    );
+    assert_eq!(seams[6], BlockSeams::NONE);
+}
+
+#[test]
+fn merged_blocks_have_no_ghost_corners() {
+    let half_size = 0.5 / 11.0;
+    let radius = 0.02;
+    let left = Block::grid_cell(10, 10, 0, 0, half_size);
+    let mut right = Block::grid_cell(10, 10, 1, 0, half_size);
+    // Rounding leaves the second block a hair taller.
+    right.max_y += 1e-12;
+
+    let simulation_state = SimulationState {
+        blocks: vec![left, right].into(),
+        balls: vec![Ball {
+            position: Vector2::new(left.min_x + radius, left.max_y + radius),
+            velocity: Vector2::new(1.0, 0.0),
+            radius,
+            filter: CollisionFilter::default(),
+        }],
+        ..Default::default()
+    };
+
+    assert_matches!(
+        simulation_state.clone().next(&[]).unwrap().1.data,
+        EventType::Collision(CollisionData {
+            against: CollisionType::Block { index: 1, .. },
+            ..
+        })
+    );
+
+    let mut merged = simulation_state;
+    merged.merge_block_edges();
+    let (merged, event) = merged.next(&[]).unwrap();
+    assert_matches!(
+        event.data,
+        EventType::Collision(CollisionData {
+            against: CollisionType::Wall(WallType::XPositive),
+            ..
+        })
+    );
+    assert_eq!(merged.balls[0].velocity, Vector2::new(-1.0, 0.0));
+}
+
+#[test]
+fn polygon_arena() {
+    let arena_event = |event: Event<EventType>| match event.data {
+        EventType::Collision(CollisionData {
+            against:
+                CollisionType::Arena {
+                    edge,
+                    contact_position,
+                },
+            ..
+        }) => Some((edge, contact_position)),
+        _ => None,
+    };
+
+    // A rectangle arena behaves like the implicit walls, whatever its winding.
+    for arena in [
+        Arena::rectangle(10.0, 10.0),
+        Arena::new(
+            Arena::rectangle(10.0, 10.0)
+                .boundary
+                .into_iter()
+                .rev()
+                .collect(),
+        ),
+    ] {
+        let simulation_state = SimulationState {
+            arena: Some(arena),
+            ..single_ball(Vector2::new(5.0, 5.0), Vector2::new(1.0, 2.0))
+        };
+        let (state, event) = simulation_state.next(&[]).unwrap();
+        assert_relative_eq!(event.time, 2.25);
+        assert_relative_eq!(arena_event(event).unwrap().1, Vector2::new(7.25, 10.0));
+        assert_relative_eq!(state.balls[0].velocity, Vector2::new(1.0, -2.0));
+    }
+
+    // Funnel: a 45 degree wall turns a falling ball sideways.
+    let funnel = Arena::new(vec![
+        Vector2::new(0.0, 10.0),
+        Vector2::new(0.0, 5.0),
+        Vector2::new(5.0, 0.0),
+        Vector2::new(10.0, 0.0),
+        Vector2::new(10.0, 10.0),
+    ]);
+    let simulation_state = SimulationState {
+        arena: Some(funnel),
+        ..single_ball(Vector2::new(2.0, 8.0), Vector2::new(0.0, -1.0))
+    };
+    let (state, event) = simulation_state.next(&[]).unwrap();
+    assert_eq!(arena_event(event).unwrap().0, 1);
+    assert_relative_eq!(
+        state.balls[0].velocity,
+        Vector2::new(1.0, 0.0),
+        epsilon = 1e-12
+    );
+
+    // L shaped arena: aiming straight at the inner corner bounces straight back.
+    let l_shape = Arena::new(vec![
+        Vector2::new(0.0, 0.0),
+        Vector2::new(10.0, 0.0),
+        Vector2::new(10.0, 5.0),
+        Vector2::new(5.0, 5.0),
+        Vector2::new(5.0, 10.0),
+        Vector2::new(0.0, 10.0),
+    ]);
+    let simulation_state = SimulationState {
+        arena: Some(l_shape),
+        ..single_ball(Vector2::new(2.0, 2.0), Vector2::new(1.0, 1.0))
+    };
+    let (state, event) = simulation_state.next(&[]).unwrap();
+    assert_relative_eq!(arena_event(event).unwrap().1, Vector2::new(5.0, 5.0));
+    assert_relative_eq!(
+        state.balls[0].velocity,
+        Vector2::new(-1.0, -1.0),
+        epsilon = 1e-12
+    );
+
+    // Holes are obstacles.
+    let simulation_state = SimulationState {
+        arena: Some(Arena::rectangle(10.0, 10.0).with_hole(vec![
+            Vector2::new(4.0, 4.0),
+            Vector2::new(4.0, 6.0),
+            Vector2::new(6.0, 6.0),
+            Vector2::new(6.0, 4.0),
+        ])),
+        ..single_ball(Vector2::new(5.0, 1.0), Vector2::new(0.0, 1.0))
+    };
+    let (state, event) = simulation_state.next(&[]).unwrap();
+    assert_eq!(arena_event(event).unwrap().0, 4 + 3);
+    assert_relative_eq!(event.time, 2.5);
+    assert_eq!(state.balls[0].velocity, Vector2::new(0.0, -1.0));
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn parallel_search_matches_serial() {
+    use crate::EventSearch;
+
+    let mut serial = SimulationState {
+        space_width: 1.0,
+        space_height: 1.0,
+        blocks: (0..10)
+            .flat_map(|y| (0..10).map(move |x| Block::grid_cell(10, 10, x, y, 0.04)))
+            .filter(|block| block.min_y > 0.3)
+            .collect(),
+        // Mirrored pairs of balls tie on every wall bounce.
+        balls: (0..16)
+            .map(|i| {
+                let angle = 0.2 + 2.7 * (i / 2) as f64 / 8.0;
+                let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+                Ball {
+                    position: Vector2::new(0.5 + side * 0.2, 0.05),
+                    velocity: Vector2::new(side * angle.cos(), angle.sin()),
+                    radius: 0.02,
+                    filter: CollisionFilter::default(),
+                }
+            })
+            .collect(),
+        search: EventSearch::Serial,
+        ..Default::default()
+    };
+    let mut parallel = SimulationState {
+        search: EventSearch::Parallel,
+        ..serial.clone()
+    };
+
+    for _ in 0..200 {
+        let (next_serial, serial_event) = serial.next(&[]).unwrap();
+        let (next_parallel, parallel_event) = parallel.next(&[]).unwrap();
+        assert_eq!(format!("{serial_event:?}"), format!("{parallel_event:?}"));
+        serial = next_serial;
+        parallel = next_parallel;
+    }
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn animated_export() {
+    use crate::AnimationOptions;
+
+    let mut state = SimulationState {
+        blocks: vec![
+            Block::new(6.0, 4.0, 4.0, 6.0),
+            Block::new(9.0, 8.0, 0.0, 1.0),
+        ]
+        .into(),
+        ..single_ball(Vector2::new(5.0, 1.0), Vector2::new(0.0, 1.0))
+    };
+    let initial = state.clone();
+
+    let mut breaking =
+        |_: &SimulationState, event: &Event<EventType>, response: &mut CollisionResponse| {
+            if let EventType::Collision(CollisionData {
+                against: CollisionType::Block { .. },
+                ..
+            }) = event.data
+            {
+                response.remove_block = true;
+            }
+        };
+    let mut steps = vec![];
+    for _ in 0..4 {
+        let (next_state, event) = state.next_with(&[], &mut breaking).unwrap();
+        state = next_state.clone();
+        steps.push((next_state, event));
+    }
+
+    let document = initial
+        .save_animation(
+            &steps,
+            &AnimationOptions {
+                trajectory_color: Some("gray".to_string()),
+                ..Default::default()
+            },
+        )
+        .to_string();
+
+    assert!(document.contains("viewBox=\"0 0 1000 1000\""));
+    assert_eq!(document.matches("<rect").count(), 3);
+    assert_eq!(document.matches("<polyline").count(), 1);
+    // Ball position in both coordinates and the broken block's visibility
+    assert_eq!(document.matches("<animate").count(), 3);
+    assert!(document.contains("attributeName=\"visibility\""));
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn svg_round_trip() {
+    use crate::SVG_SCALE;
+
+    let state = SimulationState {
+        blocks: vec![
+            Block::new(0.6, 0.4, 0.3, 0.5),
+            Block::new(0.9, 0.8, 0.0, 0.1),
+        ]
+        .into(),
+        balls: vec![Ball {
+            position: Vector2::new(0.5, 0.1),
+            velocity: Vector2::new(0.5, 1.0),
+            radius: 0.02,
+            filter: CollisionFilter::default(),
+        }],
+        ..Default::default()
+    };
+    // The second page must not leak into the scene
+    let document = state.save_img(svg::Document::new(), 0);
+    let document = state.save_img(document, 1).to_string();
+
+    let loaded = SimulationState::from_svg(&document, SVG_SCALE).unwrap();
+    assert_relative_eq!(loaded.space_width, 1.0);
+    assert_relative_eq!(loaded.space_height, 1.0);
+    assert_eq!(loaded.balls.len(), 1);
+    assert_relative_eq!(
+        loaded.balls[0].position,
+        state.balls[0].position,
+        epsilon = 1e-9
+    );
+    assert_relative_eq!(
+        loaded.balls[0].velocity,
+        state.balls[0].velocity,
+        epsilon = 1e-6
+    );
+    assert_relative_eq!(
+        loaded.balls[0].radius,
+        state.balls[0].radius,
+        epsilon = 1e-9
+    );
+    assert_eq!(loaded.blocks.len(), 2);
+    for (loaded, block) in loaded.blocks.iter().zip(&state.blocks) {
+        assert_relative_eq!(loaded.max_y, block.max_y, epsilon = 1e-9);
+        assert_relative_eq!(loaded.min_y, block.min_y, epsilon = 1e-9);
+        assert_relative_eq!(loaded.min_x, block.min_x, epsilon = 1e-9);
+        assert_relative_eq!(loaded.max_x, block.max_x, epsilon = 1e-9);
+    }
+}
+
+#[test]
+#[cfg(feature = "svg")]
+fn inkscape_svg_import() {
+    use crate::SvgImportError;
+
+    let document = r##"<svg xmlns="http://www.w3.org/2000/svg" width="210mm" height="297mm" viewBox="0 0 210 297">
+  <g inkscape:label="Layer 1" inkscape:groupmode="layer" transform="translate(10,20)">
+    <rect style="fill:none;stroke:#000000;stroke-width:0.3" x="0" y="0" width="100" height="50" />
+    <rect style="fill:#1a5fb4" x="40" y="10" width="20" height="10" />
+    <circle style="fill:#ff681d" cx="50" cy="45" r="2" />
+    <path style="fill:none;stroke:#000000" d="m 50,45 h 1 v -2" />
+  </g>
+  <circle cx="500" cy="500" r="2" />
+</svg>"##;
+
+    let state = SimulationState::from_svg(document, 10.0).unwrap();
+    assert_relative_eq!(state.space_width, 10.0);
+    assert_relative_eq!(state.space_height, 5.0);
+    assert_eq!(state.blocks.len(), 1);
+    let block = state.blocks[0];
+    assert_relative_eq!(
+        [block.max_y, block.min_y, block.min_x, block.max_x].as_slice(),
+        [4.0, 3.0, 4.0, 6.0].as_slice()
+    );
+    assert_eq!(state.balls.len(), 1);
+    assert_relative_eq!(state.balls[0].position, Vector2::new(5.0, 0.5));
+    assert_relative_eq!(state.balls[0].radius, 0.2);
+    assert_relative_eq!(
+        state.balls[0].velocity,
+        Vector2::new(5.0, 10.0),
+        epsilon = 1e-9
+    );
+
+    assert_eq!(
+        SimulationState::from_svg("<svg></svg>", 10.0).unwrap_err(),
+        SvgImportError::MissingFrame
+    );
+}
+
+#[test]
+#[cfg(feature = "cli")]
+fn example_scene_loads() {
+    let state: SimulationState = ron::from_str(include_str!("../scenes/example.ron")).unwrap();
+    assert_eq!(state.balls.len(), 2);
+    assert_eq!(state.blocks.len(), 4);
+    assert_eq!(state.blocks[0].filter, CollisionFilter::default());
+    assert_eq!(state.blocks[0].solid, BlockEdges::ALL);
+    assert_eq!(state.space_width, 1.0);
+
+    let json = serde_json::to_string(&state).unwrap();
+    let reloaded: SimulationState = serde_json::from_str(&json).unwrap();
+    assert_eq!(reloaded.balls[1].velocity, state.balls[1].velocity);
+}
+
+#[test]
+fn snapshots_share_blocks_until_changed() {
+    let mut state = SimulationState {
+        blocks: (0..10)
+            .map(|i| Block::new(0.9, 0.8, i as f64 * 0.1, i as f64 * 0.1 + 0.05))
+            .collect(),
+        balls: vec![Ball {
+            position: Vector2::new(0.525, 0.1),
+            velocity: Vector2::new(0.0, 1.0),
+            radius: 0.02,
+            filter: CollisionFilter::default(),
+        }],
+        ..Default::default()
+    };
+
+    let snapshot = state.snapshot();
+    assert!(snapshot.state().blocks.is_shared_with(&state.blocks));
+
+    let mut breaking =
+        |_: &SimulationState, event: &Event<EventType>, response: &mut CollisionResponse| {
+            response.remove_block = matches!(
+                event.data,
+                EventType::Collision(CollisionData {
+                    against: CollisionType::Block { .. },
+                    ..
+                })
+            );
+        };
+    state = state.next_with(&[], &mut breaking).unwrap().0;
+    assert_eq!(state.blocks.len(), 9);
+    assert_eq!(snapshot.state().blocks.len(), 10);
+    assert!(!snapshot.state().blocks.is_shared_with(&state.blocks));
+
+    state.restore(&snapshot);
+    assert_eq!(state.time, 0.0);
+    assert_eq!(state.blocks.len(), 10);
+    assert!(snapshot.state().blocks.is_shared_with(&state.blocks));
+}
+
+#[test]
+fn bounded_history() {
+    let mut state = SimulationState {
+        balls: vec![Ball {
+            position: Vector2::new(0.5, 0.5),
+            velocity: Vector2::new(0.3, 1.0),
+            radius: 0.02,
+            filter: CollisionFilter::default(),
+        }],
+        ..Default::default()
+    };
+    let mut history = History::new(3);
+
+    let mut times = vec![];
+    for _ in 0..5 {
+        history.record(&state);
+        times.push(state.time);
+        state = state.next(&[]).unwrap().0;
+    }
+    assert_eq!(history.len(), 3);
+    assert_eq!(history.get(0).unwrap().state().time, times[4]);
+    assert_eq!(history.get(2).unwrap().state().time, times[2]);
+    assert!(history.get(3).is_none());
+
+    for &time in times.iter().rev().take(3) {
+        assert!(history.undo(&mut state));
+        assert_eq!(state.time, time);
+    }
+    assert!(!history.undo(&mut state));
+    assert_eq!(state.time, times[2]);
+}
+
+#[test]
+fn stepping_backwards() {
+    let mut state = SimulationState {
+        blocks: (0..4)
+            .map(|i| Block::new(0.7, 0.6, i as f64 * 0.25 + 0.05, i as f64 * 0.25 + 0.2))
+            .collect(),
+        sensors: vec![Sensor::new(0.4, 0.3, 0.0, 0.5)],
+        balls: vec![
+            Ball {
+                position: Vector2::new(0.5, 0.1),
+                velocity: Vector2::new(0.3, 1.0),
+                radius: 0.02,
+                filter: CollisionFilter::default(),
+            },
+            Ball {
+                position: Vector2::new(0.2, 0.2),
+                velocity: Vector2::new(-0.7, 0.4),
+                radius: 0.03,
+                filter: CollisionFilter::default(),
+            },
+        ],
+        ..Default::default()
+    };
+
+    // Blocks break on the first hit, balls leave through the floor and the
+    // first sensor exits spawn new balls
+    let mut spawns = 3;
+    let mut rules =
+        |state: &SimulationState, event: &Event<EventType>, response: &mut CollisionResponse| {
+            match event.data {
+                EventType::Collision(CollisionData { against, .. }) => match against {
+                    CollisionType::Block { .. } => response.remove_block = true,
+                    CollisionType::Wall(WallType::YNegative) => response.remove_ball = true,
+                    _ => {}
+                },
+                EventType::Sensor(SensorData {
+                    ball,
+                    kind: SensorEventType::Exit,
+                    ..
+                }) if spawns > 0 => {
+                    spawns -= 1;
+                    response.spawn.push(Ball {
+                        velocity: -state.balls[ball].velocity,
+                        ..state.balls[ball]
+                    })
+                }
+                _ => {}
+            }
+        };
+
+    let mut history = EventHistory::new();
+    let mut states = vec![state.clone()];
+    while let Some((next_state, _)) = state.clone().next_recorded(&[], &mut rules, &mut history) {
+        state = next_state;
+        states.push(state.clone());
+    }
+    assert!(state.balls.is_empty());
+    assert!(history.len() > 10);
+    assert_eq!(history.len(), states.len() - 1);
+
+    for expected in states.iter().rev().skip(1) {
+        state = state.prev(&mut history).unwrap().0;
+        assert_relative_eq!(state.time, expected.time, epsilon = 1e-9);
+        assert_eq!(state.balls.len(), expected.balls.len());
+        assert_eq!(state.blocks.len(), expected.blocks.len());
+        for (ball, expected) in state.balls.iter().zip(&expected.balls) {
+            assert_relative_eq!(ball.position, expected.position, epsilon = 1e-9);
+            assert_eq!(ball.velocity, expected.velocity);
+        }
+    }
+    assert!(history.is_empty());
+    assert!(state.prev(&mut history).is_none());
+}
+
+#[test]
+fn stall_detection() {
+    let flat = SimulationState {
+        balls: vec![Ball {
+            position: Vector2::new(0.5, 0.5),
+            velocity: Vector2::new(1.0, 0.001),
+            radius: 0.02,
+            filter: CollisionFilter::default(),
+        }],
+        stall_detection: Some(StallDetection::default()),
+        ..Default::default()
+    };
+
+    // Nudged down to the minimum angle right after the first bounce
+    let (state, _) = flat.clone().next(&[]).unwrap();
+    let (state, event) = state.next(&[]).unwrap();
+    assert_matches!(
+        event,
+        Event {
+            time: 0.0,
+            data: EventType::Stalled(StallData {
+                ball: 0,
+                reason: StallReason::NearHorizontal
+            })
+        }
+    );
+    let velocity = state.balls[0].velocity;
+    assert_relative_eq!(velocity.norm(), flat.balls[0].velocity.norm());
+    assert_relative_eq!(velocity.y.atan2(-velocity.x), -0.05);
+    assert_matches!(
+        state.next(&[]).unwrap().1.data,
+        EventType::Collision(CollisionData { .. })
+    );
+
+    let (state, _) = SimulationState {
+        stall_detection: Some(StallDetection {
+            policy: StallPolicy::RemoveBall,
+            ..Default::default()
+        }),
+        ..flat.clone()
+    }
+    .next(&[])
+    .unwrap();
+    assert!(state.next(&[]).unwrap().0.balls.is_empty());
+
+    // Straight up and down hits the ceiling again in the same way
+    let mut state = SimulationState {
+        balls: vec![Ball {
+            velocity: Vector2::new(0.0, 1.0),
+            ..flat.balls[0]
+        }],
+        stall_detection: Some(StallDetection {
+            policy: StallPolicy::Report,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut events = vec![];
+    for _ in 0..4 {
+        let (next_state, event) = state.next(&[]).unwrap();
+        state = next_state;
+        events.push(event.data);
+    }
+    assert_matches!(
+        events[3],
+        EventType::Stalled(StallData {
+            reason: StallReason::Periodic,
+            ..
+        })
+    );
+    assert_eq!(state.balls[0].velocity, Vector2::new(0.0, -1.0));
+
+    let mut state = SimulationState {
+        stall_detection: Some(StallDetection {
+            policy: StallPolicy::Report,
+            max_events: 3,
+            rate_window: 10.0,
+            ..Default::default()
+        }),
+        ..flat
+    };
+    state.balls[0].velocity = Vector2::new(1.0, 1.0);
+    for _ in 0..4 {
+        state = state.next(&[]).unwrap().0;
+    }
+    assert_matches!(
+        state.next(&[]).unwrap().1.data,
+        EventType::Stalled(StallData {
+            reason: StallReason::EventRate,
+            ..
+        })
+    );
+}
+
+#[test]
+fn emitter() {
+    let min_angle = 0.2;
+    for (direction, expected) in [
+        (Vector2::new(0.0, 1.0), PI / 2.0),
+        (Vector2::new(1.0, 0.0), min_angle),
+        (Vector2::new(-1.0, 0.01), PI - min_angle),
+        (Vector2::new(1.0, -1.0), min_angle),
+        (Vector2::new(-1.0, -1.0), PI - min_angle),
+    ] {
+        let clamped = Emitter::clamp_direction(direction, min_angle);
+        assert_relative_eq!(clamped.norm(), 1.0);
+        assert_relative_eq!(clamped.y.atan2(clamped.x), expected);
+    }
+
+    let emitter = Emitter {
+        position: Vector2::new(0.3, 0.0),
+        direction: Vector2::new(3.0, 4.0),
+        speed: 2.0,
+        interval: 0.25,
+        ..Default::default()
+    };
+    let ball = emitter.ball();
+    assert_eq!(ball.position, Vector2::new(0.3, 0.0));
+    assert_relative_eq!(ball.velocity, Vector2::new(1.2, 1.6));
+
+    assert_relative_eq!(emitter.next_launch(0.0).time, 0.25);
+    assert_relative_eq!(emitter.next_launch(0.1).time, 0.15);
+    assert_relative_eq!(emitter.next_launch(0.25).time, 0.25);
+}
+
+#[test]
+fn removed_ball() {
+    let state = SimulationState {
+        balls: vec![
+            single_ball(Vector2::new(5.0, 5.0), Vector2::new(0.0, -1.0)).balls[0],
+            single_ball(Vector2::new(2.0, 5.0), Vector2::new(0.0, 1.0)).balls[0],
+        ],
+        ..single_ball(Vector2::new(5.0, 5.0), Vector2::new(0.0, -1.0))
+    };
+    let (mut next_state, event) = state.clone().next(&[]).unwrap();
+    assert_eq!(event.ball(), Some(0));
+    let expected_position = |index: usize| {
+        let ball = state.balls.get(index)?;
+        Some(ball.position + ball.velocity * next_state.time)
+    };
+
+    // A bounce keeps the ball where it was headed
+    assert_eq!(next_state.removed_ball(&event, expected_position), None);
+
+    // The other ball moving into its index doesn't count as the same one
+    next_state.balls.remove(0);
+    assert_eq!(next_state.removed_ball(&event, expected_position), Some(0));
+
+    // Balls the caller doesn't follow are never reported
+    assert_eq!(next_state.removed_ball(&event, |_| None), None);
+    assert_eq!(
+        next_state.removed_ball(
+            &Event {
+                time: 0.0,
+                data: EventType::Custom
+            },
+            expected_position
+        ),
+        None
+    );
+}
@@ -1,19 +1,29 @@
 mod accept_user_input;
 mod animate_blocks_in;
+mod difficulty;
+mod game_over;
+mod hud;
+mod launcher;
 mod play_simulation;
 mod utils;
 
 use std::f32::consts::PI;
 
-use crate::{colors, despawn_screen, GameState};
+use crate::{colors, despawn_screen, storage, GameState};
+use ball_game_core::{Board, Difficulty};
 use bevy::prelude::*;
 use bevy::sprite::MaterialMesh2dBundle;
+use bevy::{app::AppExit, window::WindowCloseRequested};
 use nalgebra::Vector2;
-use ndarray::Array2;
+use serde::{Deserialize, Serialize};
 
 use self::accept_user_input::AcceptUserInputPlugin;
 use self::animate_blocks_in::AnimateBlocksInPlugin;
-use self::play_simulation::PlaySimulationPlugin;
+use self::difficulty::{DifficultyPlugin, GameDifficulty};
+use self::game_over::GameOverPlugin;
+use self::hud::HudPlugin;
+use self::launcher::LauncherPlugin;
+use self::play_simulation::{stop_shot, PlaySimulationPlugin, Simulation};
 
 #[derive(Component)]
 pub struct OnGame;
@@ -27,47 +37,117 @@ enum InnerGameState {
     AnimateBlocksIn,
     AcceptUserInput,
     PlaySimulation,
+    GameOver,
 }
 
-#[derive(Component)]
+/// How a game started from the menu begins.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GameStart {
+    /// New game with a random seed.
+    #[default]
+    New,
+    /// New game with rows decided by the given seed.
+    Seeded(u64),
+    Continue,
+}
+
+impl GameStart {
+    /// Seed for a new game, restarting a seeded game plays the same rows again.
+    fn seed(self) -> u64 {
+        match self {
+            GameStart::Seeded(seed) => seed,
+            GameStart::New | GameStart::Continue => random_seed(),
+        }
+    }
+}
+
+/// Kept short enough to be read out and typed back in.
+fn random_seed() -> u64 {
+    rand::random::<u32>().into()
+}
+
+/// Where the current round is, which is where a saved game picks up again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Phase {
+    NewRow,
+    Aiming,
+    Over,
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
 struct BoardState {
-    blocks: Array2<usize>,
-    ball_count: usize,
-    launcher_position: f64,
+    board: Board,
+    #[serde(skip, default = "Vector2::zeros")]
     direction: Vector2<f64>,
+    phase: Phase,
+}
+
+impl BoardState {
+    fn new(seed: u64, difficulty: Difficulty) -> Self {
+        let mut board = Board::new(10, 10, seed);
+        board.difficulty = difficulty;
+        Self {
+            board,
+            direction: Vector2::zeros(),
+            phase: Phase::NewRow,
+        }
+    }
+}
+
+pub fn has_saved_game() -> bool {
+    storage::exists(storage::SAVED_GAME)
+}
+
+/// Fading lines drawn behind moving balls.
+#[derive(Resource)]
+struct TrailSettings {
+    /// Simulation time covered by a trail.
+    length: f64,
+    /// Colour at the ball, the trail fades out from it.
+    color: Color,
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        Self {
+            length: 0.15,
+            color: *colors::BALLS,
+        }
+    }
 }
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_state::<InnerGameState>()
+            .init_resource::<TrailSettings>()
+            .init_resource::<GameStart>()
             .add_systems(
                 OnEnter(GameState::Game),
-                (
-                    game_setup,
-                    add_game_rectangle,
-                    initialize_camera,
-                    initialize_game_state,
-                ),
+                (add_game_rectangle, initialize_camera, initialize_game_state),
             )
             .add_plugin(AnimateBlocksInPlugin)
             .add_plugin(AcceptUserInputPlugin)
             .add_plugin(PlaySimulationPlugin)
+            .add_plugin(GameOverPlugin)
+            .add_plugin(HudPlugin)
+            .add_plugin(LauncherPlugin)
+            .add_plugin(DifficultyPlugin)
             .add_systems(Update, escape_to_menu.run_if(in_state(GameState::Game)))
             .add_systems(OnEnter(InnerGameState::Inactive), on_inactive)
+            .add_systems(Last, save_on_exit.run_if(in_state(GameState::Game)))
             .add_systems(
                 OnExit(GameState::Game),
                 (
                     set_inactive_game_state,
-                    despawn_screen::<OnGame>.after(set_inactive_game_state),
+                    save_game,
+                    despawn_screen::<OnGame>
+                        .after(set_inactive_game_state)
+                        .after(save_game),
                 ),
             );
     }
 }
 
-fn game_setup(mut inner_game_state: ResMut<NextState<InnerGameState>>) {
-    inner_game_state.set(InnerGameState::AnimateBlocksIn);
-}
-
 fn add_game_rectangle(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -87,16 +167,62 @@ fn add_game_rectangle(
     ));
 }
 
-fn initialize_game_state(mut commands: Commands) {
-    commands.spawn((
-        BoardState {
-            blocks: Array2::default((10, 10)),
-            ball_count: 1,
-            launcher_position: 0.5,
-            direction: Vector2::zeros(),
-        },
-        OnGame,
-    ));
+fn initialize_game_state(
+    mut commands: Commands,
+    start: Res<GameStart>,
+    difficulty: Res<GameDifficulty>,
+    mut inner_game_state: ResMut<NextState<InnerGameState>>,
+) {
+    let board_state = match *start {
+        GameStart::New | GameStart::Seeded(_) => BoardState::new(start.seed(), difficulty.0),
+        GameStart::Continue => storage::load(storage::SAVED_GAME)
+            .map(|mut board_state: BoardState| {
+                board_state.board.difficulty = difficulty.0;
+                board_state
+            })
+            .unwrap_or_else(|| BoardState::new(random_seed(), difficulty.0)),
+    };
+    inner_game_state.set(match board_state.phase {
+        Phase::Aiming => InnerGameState::AcceptUserInput,
+        Phase::NewRow | Phase::Over => InnerGameState::AnimateBlocksIn,
+    });
+    commands.spawn((board_state, OnGame));
+}
+
+fn save_game(board_state: Query<&BoardState>) {
+    write_saved_game(board_state.single());
+}
+
+fn write_saved_game(board_state: &BoardState) {
+    if board_state.phase == Phase::Over {
+        storage::remove(storage::SAVED_GAME);
+    } else {
+        storage::save(storage::SAVED_GAME, board_state);
+    }
+}
+
+// Quitting doesn't leave the game state, so save on the way out as well. A
+// shot in flight is stopped first, like leaving PlaySimulation does, so the
+// saved board can't undo it.
+fn save_on_exit(
+    mut app_exit: EventReader<AppExit>,
+    mut window_close: EventReader<WindowCloseRequested>,
+    board_state: Query<&BoardState>,
+    simulation: Query<&Simulation>,
+) {
+    if !app_exit.is_empty() || !window_close.is_empty() {
+        app_exit.clear();
+        window_close.clear();
+        let board_state = board_state.single();
+        match simulation.get_single() {
+            Ok(simulation) => {
+                let mut board_state = board_state.clone();
+                stop_shot(&mut board_state, simulation);
+                write_saved_game(&board_state);
+            }
+            Err(_) => write_saved_game(board_state),
+        }
+    }
 }
 
 fn initialize_camera(mut commands: Commands) {
@@ -1,10 +1,11 @@
+use ball_simulation::Emitter;
 use bevy::{math::vec2, prelude::*, sprite::MaterialMesh2dBundle};
 use nalgebra::Vector2;
 
 use crate::{colors, despawn_screen};
 
 use super::{
-    utils::{add_blocks_from_state, get_block},
+    utils::{add_blocks_from_state, add_pickups_from_state},
     BoardState, InnerGameState,
 };
 
@@ -49,13 +50,7 @@ fn generate_graphic_blocks(
     board_state: Query<&mut BoardState>,
     assets: Res<AssetServer>,
 ) {
-    let mut blocks = vec![];
-    let [h, w]: [usize; 2] = board_state.single().blocks.shape().try_into().unwrap();
-    for ((y, x), &lives) in board_state.single().blocks.indexed_iter() {
-        if lives > 0 {
-            blocks.push((get_block(w, h, x, y), lives));
-        }
-    }
+    let board = &board_state.single().board;
 
     let mut block_ids = BlockEntities::default();
     let blocks_parent = commands
@@ -65,8 +60,16 @@ fn generate_graphic_blocks(
             OnAcceptUserInput,
         ))
         .id();
+    add_pickups_from_state(
+        &board.pickups(),
+        &mut vec![],
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        blocks_parent,
+    );
     add_blocks_from_state(
-        &blocks,
+        &board.blocks(),
         &mut block_ids.0,
         &mut commands,
         &mut meshes,
@@ -81,7 +84,7 @@ fn generate_graphic_blocks(
         .spawn((
             SpatialBundle {
                 visibility: Visibility::Hidden,
-                transform: Transform::from_xyz(board_state.single().launcher_position as f32, 0.0, -0.5),
+                transform: Transform::from_xyz(board.launcher_position as f32, 0.0, -0.5),
                 ..default()
             },
             AimIndicator::default(),
@@ -122,10 +125,11 @@ fn update(
             .truncate();
 
         if world_position.y > 0.0 {
-            let start = Vector2::new(board_state.single().launcher_position, 0.0);
+            let board = &board_state.single().board;
+            let start = Vector2::new(board.launcher_position, 0.0);
             let end = Vector2::new(world_position.x, world_position.y).cast();
 
-            let delta = (end - start).normalize();
+            let delta = Emitter::clamp_direction(end - start, board.launch.min_angle);
 
             aim_indicator.single_mut().direction = Some(delta);
         }
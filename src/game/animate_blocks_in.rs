@@ -1,13 +1,11 @@
 use bevy::prelude::*;
-use ndarray::{ArrayView1, Axis};
-use rand::distributions::uniform::SampleRange;
 use simple_easing::cubic_out;
 
 use crate::despawn_screen;
 
 use super::{
-    utils::{add_blocks_from_state, get_block, get_block_separations},
-    BoardState, InnerGameState,
+    utils::{add_blocks_from_state, add_pickups_from_state, get_block_separations},
+    BoardState, InnerGameState, Phase,
 };
 
 #[derive(Component)]
@@ -47,24 +45,17 @@ struct BallEntities(Vec<Entity>);
 #[derive(Component, Default)]
 struct BlocksParent;
 
-fn generate_new_blocks(mut board_state: Query<&mut BoardState>) {
+fn generate_new_blocks(
+    mut board_state: Query<&mut BoardState>,
+    mut inner_game_state: ResMut<NextState<InnerGameState>>,
+) {
     let mut board_state = board_state.single_mut();
-    let mut rng = rand::thread_rng();
-
-    let [_h, w]: [usize; 2] = board_state.blocks.shape().try_into().unwrap();
-    let mut blocks = vec![0; w];
-    for block in blocks.iter_mut() {
-        if (0.0..=1.0).sample_single(&mut rng) > 0.5 {
-            *block = 4;
-        }
+    if board_state.board.advance_row().is_ok() {
+        board_state.phase = Phase::Aiming;
+    } else {
+        board_state.phase = Phase::Over;
+        inner_game_state.set(InnerGameState::GameOver);
     }
-
-    board_state
-        .blocks
-        .push_row(ArrayView1::from(&blocks))
-        .unwrap();
-
-    board_state.blocks.remove_index(Axis(0), 0);
 }
 
 fn generate_graphic_blocks(
@@ -74,13 +65,7 @@ fn generate_graphic_blocks(
     board_state: Query<&mut BoardState>,
     assets: Res<AssetServer>,
 ) {
-    let mut blocks = vec![];
-    let [h, w]: [usize; 2] = board_state.single().blocks.shape().try_into().unwrap();
-    for ((y, x), &lives) in board_state.single().blocks.indexed_iter() {
-        if lives > 0 {
-            blocks.push((get_block(w, h, x, y), lives));
-        }
-    }
+    let board = &board_state.single().board;
 
     let mut block_ids = BlockEntities::default();
     let blocks_parent = commands
@@ -90,8 +75,16 @@ fn generate_graphic_blocks(
             OnAnimateBlocksIn,
         ))
         .id();
+    add_pickups_from_state(
+        &board.pickups(),
+        &mut vec![],
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        blocks_parent,
+    );
     add_blocks_from_state(
-        &blocks,
+        &board.blocks(),
         &mut block_ids.0,
         &mut commands,
         &mut meshes,
@@ -121,8 +114,8 @@ fn animate(
     let easing = cubic_out(timer.percent());
 
     let movement = {
-        let [rows, columns]: [usize; 2] = board_state.single().blocks.shape().try_into().unwrap();
-        get_block_separations(columns, rows).y
+        let board = &board_state.single().board;
+        get_block_separations(board.columns(), board.rows()).y
     };
 
     blocks_parent.single_mut().translation.y = (1.0 - easing) * movement as f32;
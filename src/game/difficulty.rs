@@ -0,0 +1,83 @@
+use ball_game_core::Difficulty;
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::BoxedFuture,
+};
+
+const DIFFICULTY_FILE: &str = "difficulty.ron";
+
+/// Row balancing for new games, read from [`DIFFICULTY_FILE`] in the assets so
+/// it can be tuned without rebuilding. Keeps the defaults until the file has
+/// loaded, and when it can't be parsed.
+#[derive(Resource, Default, Clone, Copy)]
+pub(super) struct GameDifficulty(pub(super) Difficulty);
+
+#[derive(TypeUuid, TypePath)]
+#[uuid = "1df7e088-ae7b-496b-b5cb-a7335b1d968a"]
+struct DifficultyAsset(Difficulty);
+
+#[derive(Resource)]
+struct DifficultyHandle(Handle<DifficultyAsset>);
+
+#[derive(Default)]
+struct DifficultyLoader;
+
+impl AssetLoader for DifficultyLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let difficulty = ron::de::from_bytes(bytes).map_err(|error| {
+                error!(
+                    "Using default difficulty, could not parse {}: {error}",
+                    load_context.path().display()
+                );
+                error
+            })?;
+            load_context.set_default_asset(LoadedAsset::new(DifficultyAsset(difficulty)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        // Loaders are picked by what follows the first dot of the file name,
+        // this is the only RON asset
+        &["ron"]
+    }
+}
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<DifficultyAsset>()
+            .init_asset_loader::<DifficultyLoader>()
+            .init_resource::<GameDifficulty>()
+            .add_systems(Startup, load_difficulty)
+            .add_systems(Update, update_difficulty);
+    }
+}
+
+fn load_difficulty(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(DifficultyHandle(assets.load(DIFFICULTY_FILE)));
+}
+
+// Picks up the file once it has loaded, and again whenever it changes
+fn update_difficulty(
+    mut events: EventReader<AssetEvent<DifficultyAsset>>,
+    source: Res<DifficultyHandle>,
+    assets: Res<Assets<DifficultyAsset>>,
+    mut difficulty: ResMut<GameDifficulty>,
+) {
+    for event in events.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            if *handle == source.0 {
+                difficulty.0 = assets.get(handle).unwrap().0;
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use ball_game_core::HighScores;
+use bevy::prelude::*;
+
+use crate::{colors, despawn_screen, storage, GameState};
+
+use super::{
+    difficulty::GameDifficulty,
+    utils::{add_blocks_from_state, add_pickups_from_state},
+    BoardState, GameStart, InnerGameState,
+};
+
+#[derive(Component)]
+struct OnGameOver;
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(InnerGameState::GameOver),
+            (generate_graphic_blocks, game_over_setup),
+        )
+        .add_systems(
+            Update,
+            game_over_action
+                .run_if(in_state(InnerGameState::GameOver))
+                .run_if(in_state(GameState::Game)),
+        )
+        .add_systems(
+            OnExit(InnerGameState::GameOver),
+            despawn_screen::<OnGameOver>,
+        );
+    }
+}
+
+#[derive(Component)]
+enum GameOverButtonAction {
+    Restart,
+    Menu,
+}
+
+fn generate_graphic_blocks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    board_state: Query<&BoardState>,
+    assets: Res<AssetServer>,
+) {
+    let board = &board_state.single().board;
+
+    let blocks_parent = commands
+        .spawn((SpatialBundle::INHERITED_IDENTITY, OnGameOver))
+        .id();
+    add_pickups_from_state(
+        &board.pickups(),
+        &mut vec![],
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        blocks_parent,
+    );
+    add_blocks_from_state(
+        &board.blocks(),
+        &mut vec![],
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        blocks_parent,
+        assets,
+    );
+}
+
+fn game_over_setup(
+    mut commands: Commands,
+    board_state: Query<&BoardState>,
+    assets: Res<AssetServer>,
+) {
+    let board = &board_state.single().board;
+    let score = board.score();
+    let mut high_scores: HighScores = storage::load(storage::HIGH_SCORES).unwrap_or_default();
+    let rank = high_scores.insert(score);
+    if rank.is_some() {
+        storage::save(storage::HIGH_SCORES, &high_scores);
+    }
+
+    let font = assets.load::<Font, _>("fonts/OpenSans-Regular.ttf");
+
+    let text_style = TextStyle {
+        font_size: 40.0,
+        color: *colors::LIGHT_TEXT,
+        font: font.clone(),
+    };
+
+    let button_style = Style {
+        margin: UiRect::all(Val::Px(20.0)),
+        size: Size::new(Val::Px(160.0), Val::Px(50.0)),
+        display: Display::Flex,
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            OnGameOver,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Game Over",
+                TextStyle {
+                    font_size: 60.0,
+                    color: *colors::LIGHT_TEXT,
+                    font,
+                },
+            ));
+            let mut summary = format!(
+                "Score {} on turn {}\nSeed {}",
+                score.points, score.round, board.seed
+            );
+            match rank {
+                Some(0) => summary.push_str("\nNew high score!"),
+                Some(rank) => summary.push_str(&format!("\n#{} in high scores", rank + 1)),
+                None => {}
+            }
+            parent.spawn(
+                TextBundle::from_section(summary, text_style.clone()).with_style(Style {
+                    margin: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+            for (action, label) in [
+                (GameOverButtonAction::Restart, "Restart"),
+                (GameOverButtonAction::Menu, "Menu"),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: button_style.clone(),
+                            background_color: (*colors::BUTTON_BACKGROUND).into(),
+                            ..default()
+                        },
+                        action,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(label, text_style.clone()));
+                    });
+            }
+        });
+}
+
+fn game_over_action(
+    interaction_query: Query<
+        (&Interaction, &GameOverButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut board_state: Query<&mut BoardState>,
+    start: Res<GameStart>,
+    difficulty: Res<GameDifficulty>,
+    mut inner_game_state: ResMut<NextState<InnerGameState>>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction == Interaction::Clicked {
+            match action {
+                GameOverButtonAction::Restart => {
+                    *board_state.single_mut() = BoardState::new(start.seed(), difficulty.0);
+                    inner_game_state.set(InnerGameState::AnimateBlocksIn);
+                }
+                GameOverButtonAction::Menu => inner_game_state.set(InnerGameState::Inactive),
+            }
+        }
+    }
+}
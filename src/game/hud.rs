@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+
+use crate::{colors, despawn_screen, GameState};
+
+use super::{play_simulation::Simulation, BoardState, InnerGameState};
+
+#[derive(Component)]
+struct OnHud;
+
+#[derive(Component)]
+struct HudText;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        for state in [
+            InnerGameState::AcceptUserInput,
+            InnerGameState::PlaySimulation,
+        ] {
+            app.add_systems(OnEnter(state), hud_setup)
+                .add_systems(OnExit(state), despawn_screen::<OnHud>);
+        }
+        app.add_systems(Update, update_hud.run_if(in_state(GameState::Game)));
+    }
+}
+
+fn hud_setup(mut commands: Commands, assets: Res<AssetServer>) {
+    let font = assets.load::<Font, _>("fonts/OpenSans-Regular.ttf");
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::FlexStart,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            OnHud,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 30.0,
+                        color: *colors::DARK_TEXT,
+                        font,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                }),
+                HudText,
+            ));
+        });
+}
+
+fn update_hud(
+    board_state: Query<&BoardState>,
+    simulation: Query<&Simulation>,
+    mut hud: Query<&mut Text, With<HudText>>,
+) {
+    let Ok(mut text) = hud.get_single_mut() else {
+        return;
+    };
+    let board = &board_state.single().board;
+    // Shot results only reach the board once the shot is over
+    let shot = simulation
+        .get_single()
+        .map(|simulation| simulation.turn.result())
+        .unwrap_or_default();
+
+    text.sections[0].value = format!(
+        "Turn {}    Score {}    Balls {}    Seed {}",
+        board.round,
+        board.points + shot.points,
+        board.ball_count + shot.balls_gained,
+        board.seed,
+    );
+}
@@ -0,0 +1,66 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+
+use crate::{colors, GameState};
+
+use super::{play_simulation::Simulation, BoardState, OnGame};
+
+// Fraction of the way left to the target covered each second
+const LAUNCHER_SPEED: f32 = 0.9999;
+
+#[derive(Component)]
+struct Launcher;
+
+pub struct LauncherPlugin;
+
+impl Plugin for LauncherPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Game), add_launcher)
+            .add_systems(Update, move_launcher.run_if(in_state(GameState::Game)));
+    }
+}
+
+fn add_launcher(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Circle::new(0.02).into()).into(),
+            material: materials.add(ColorMaterial::from(*colors::BALLS)),
+            // Placed by move_launcher once the board is there
+            visibility: Visibility::Hidden,
+            transform: Transform::from_xyz(0.5, 0.0, -0.6),
+            ..default()
+        },
+        Launcher,
+        OnGame,
+    ));
+}
+
+fn move_launcher(
+    time: Res<Time>,
+    board_state: Query<&BoardState>,
+    simulation: Query<&Simulation>,
+    mut launcher: Query<(&mut Transform, &mut Visibility), With<Launcher>>,
+) {
+    let (Ok(board_state), Ok((mut transform, mut visibility))) =
+        (board_state.get_single(), launcher.get_single_mut())
+    else {
+        return;
+    };
+    // Heads to where the first ball landed as soon as it does
+    let target = simulation
+        .get_single()
+        .ok()
+        .and_then(|simulation| simulation.turn.landing())
+        .unwrap_or(board_state.board.launcher_position) as f32;
+
+    if *visibility == Visibility::Hidden {
+        transform.translation.x = target;
+        *visibility = Visibility::Visible;
+    } else {
+        let progress = 1.0 - (1.0 - LAUNCHER_SPEED).powf(time.delta_seconds());
+        transform.translation.x += (target - transform.translation.x) * progress;
+    }
+}
@@ -1,15 +1,16 @@
-use super::utils::{add_ball, add_blocks_from_state, get_block, Ball, Block, Lives};
-use super::{BoardState, InnerGameState};
+use super::utils::{add_ball, add_blocks_from_state, add_pickups_from_state, Ball, Block, Lives};
+use super::{BoardState, InnerGameState, Phase, TrailSettings};
 use crate::{despawn_screen, GameState};
-use ball_simulation::SimulationState;
-use ball_simulation::*;
+use ball_game_core::{Turn, TurnEvent};
+use ball_interpolator::Interpolator;
 use bevy::time::Stopwatch;
 use bevy::{prelude::*, sprite::Mesh2dHandle};
-use nalgebra::Vector2;
-use std::ops::{Add, Div, Mul};
 
 pub struct PlaySimulationPlugin;
 
+// Pieces a whole trail is split into, so it fades smoothly along straight parts
+const TRAIL_SEGMENTS: usize = 16;
+
 #[derive(Component)]
 struct OnPlaySimulation;
 
@@ -27,7 +28,7 @@ impl Plugin for PlaySimulationPlugin {
                 update_simulation,
                 handle_stop.after(update_simulation),
                 interpolate_simulation.after(update_simulation),
-                update_balls.after(interpolate_simulation),
+                draw_trails.after(update_simulation),
             )
                 .run_if(in_state(InnerGameState::PlaySimulation)),)
                 .run_if(in_state(GameState::Game)),
@@ -43,27 +44,19 @@ impl Plugin for PlaySimulationPlugin {
 }
 
 #[derive(Component)]
-struct Simulation {
-    balls_left: usize,
-    balls_increment: usize,
-    launcher_position: f64,
-    spawn_direction: Vector2<f64>,
-    state: SimulationState,
-    next: Option<(SimulationState, ball_simulation::Event<EventType>)>,
+pub(super) struct Simulation {
+    pub(super) turn: Turn,
+    interpolator: Interpolator,
     speed: f64,
 }
 
-#[derive(Component)]
-struct InterpolatedSimulation {
-    state: SimulationState,
-}
-
 #[derive(Component, Default)]
 struct BlockEntities(Vec<Entity>);
 
 #[derive(Component, Default)]
-struct GridPosition(Vector2<usize>);
+struct PickupEntities(Vec<Entity>);
 
+// Indexed by interpolator track
 #[derive(Component, Default)]
 struct BallEntities(Vec<Entity>);
 
@@ -85,33 +78,28 @@ fn add_simulation_state(
 ) {
     commands.spawn((SimulationWatch(Stopwatch::new()), OnPlaySimulation));
 
-    let mut block_positions = vec![];
-    let mut blocks = vec![];
-
-    let [h, w]: [usize; 2] = board_state.single().blocks.shape().try_into().unwrap();
-    for ((y, x), &lives) in board_state.single().blocks.indexed_iter() {
-        if lives > 0 {
-            blocks.push((get_block(w, h, x, y), lives));
-            block_positions.push(Vector2::new(x, y));
-        }
-    }
+    let board_state = board_state.single();
+    let turn = board_state.board.aim(board_state.direction);
+    let blocks = turn
+        .state
+        .blocks
+        .iter()
+        .zip(turn.blocks())
+        .map(|(&block, &(_, lives))| (block, lives))
+        .collect::<Vec<_>>();
+    let pickups = turn
+        .state
+        .sensors
+        .iter()
+        .zip(turn.pickups())
+        .map(|(&sensor, &(_, cell))| (sensor, cell))
+        .collect::<Vec<_>>();
 
-    let simulation_state = SimulationState {
-        time: 0.0,
-        space_width: 1.0,
-        space_height: 1.0,
-        balls: vec![],
-        blocks: blocks.iter().map(|&(block, _)| block).collect(),
-    };
     commands.spawn((
         Simulation {
-            state: simulation_state.clone(),
-            next: None,
-            balls_left: board_state.single().ball_count,
-            balls_increment: 0,
-            spawn_direction: board_state.single().direction,
+            interpolator: Interpolator::new(&turn.state),
+            turn,
             speed: 1.0,
-            launcher_position: board_state.single().launcher_position,
         },
         OnPlaySimulation,
     ));
@@ -124,7 +112,17 @@ fn add_simulation_state(
             BlocksParent,
         ))
         .id();
-    let block_entities = add_blocks_from_state(
+    let mut pickup_ids = PickupEntities::default();
+    add_pickups_from_state(
+        &pickups,
+        &mut pickup_ids.0,
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        blocks_parent,
+    );
+    commands.spawn((pickup_ids, OnPlaySimulation));
+    add_blocks_from_state(
         &blocks,
         &mut block_ids.0,
         &mut commands,
@@ -133,18 +131,9 @@ fn add_simulation_state(
         blocks_parent,
         assets,
     );
-    for (block_entity, position) in block_entities.into_iter().zip(block_positions) {
-        commands.entity(block_entity).insert(GridPosition(position));
-    }
     commands.spawn((block_ids, OnPlaySimulation));
     let ball_ids = BallEntities::default();
     commands.spawn((ball_ids, OnPlaySimulation));
-    commands.spawn((
-        InterpolatedSimulation {
-            state: simulation_state,
-        },
-        OnPlaySimulation,
-    ));
     commands.spawn((
         Mesh2dHandle::from(meshes.add(shape::Circle::new(1.).into())),
         BallMesh,
@@ -178,7 +167,7 @@ fn update_simulation(
     mut simulation: Query<&mut Simulation>,
     mut ball_ids: Query<&mut BallEntities>,
     mut block_ids: Query<&mut BlockEntities>,
-    mut interpolated_simulation: Query<&mut InterpolatedSimulation>,
+    mut pickup_ids: Query<&mut PickupEntities>,
     mut commands: Commands,
     ball_mesh: Query<&Mesh2dHandle, With<BallMesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
@@ -189,124 +178,137 @@ fn update_simulation(
     let time = time.single();
     let mut ball_ids = ball_ids.single_mut();
     let mut block_ids = block_ids.single_mut();
+    let mut pickup_ids = pickup_ids.single_mut();
     let mut simulation = simulation.single_mut();
-    let mut interpolated_simulation = interpolated_simulation.single_mut();
 
-    while let Some((next_state, next_event)) = {
-        let time = simulation.state.time;
-        if simulation.next.is_none() {
-            let spawn_event = (simulation.balls_left > 0).then(||ball_simulation::Event {
-                time: time.mul(6.0).floor().add(1.0).div(6.0) - time,
-                data: EventType::Custom,
-            });
-            simulation.next = simulation.state.clone().next(spawn_event.as_slice());
-        }
-        simulation.next.as_ref()
-    } && time.0.elapsed_secs_f64() >= next_state.time {
-        let next_state = next_state.clone();
-        let next_event = *next_event;
-
-        simulation.state = next_state.clone();
-        simulation.next = None;
-        //println!("Time: {} | Event: {:?}", simulation.state.time, event);
+    while let Some(next_time) = simulation.turn.next_time()
+        && time.0.elapsed_secs_f64() >= next_time
+    {
+        let (event, turn_event) = simulation.turn.step().unwrap();
 
-        if let EventType::Custom = next_event.data {
-            let spawn_direction = simulation.spawn_direction;
-            let launcher_position = simulation.launcher_position;
-            simulation.state.balls.push(ball_simulation::Ball {
-                position: Vector2::new(launcher_position, 0.0),
-                velocity: spawn_direction * 2.0,
-                radius: 0.02,
-            });
-            simulation.balls_left -= 1;
-            add_ball(
+        match turn_event {
+            TurnEvent::Launched => add_ball(
                 &mut commands,
                 &mut ball_ids.0,
                 ball_mesh.get_single().unwrap().clone(),
                 &mut materials,
-                OnPlaySimulation
-            );
-        } else if let EventType::Collision(CollisionData { ball, against }) = next_event.data {
-            if let CollisionType::Wall(WallType::YNegative) = against {
-                let entity = ball_ids.0.remove(ball);
-                commands.entity(entity).despawn();
-                simulation.state.balls.remove(ball);
-            } else if let CollisionType::Block { index, .. } = against {
-                let hit_entity = block_ids.0[index];
+                OnPlaySimulation,
+            ),
+            TurnEvent::Returned { ball } => {
+                let track = simulation.interpolator.track_of(ball).unwrap();
+                commands.entity(ball_ids.0[track]).despawn();
+            }
+            TurnEvent::Hit { block, lives: left } => {
+                let hit_entity = block_ids.0[block];
                 let (mut lives, children) = blocks.get_mut(hit_entity).unwrap();
-                lives.0 -= 1;
-                let mut text = blocks_texts.get_mut(*children.into_iter().find(|&&child| blocks_texts.contains(child)).unwrap()).unwrap();
+                lives.0 = left;
+                let mut text = blocks_texts
+                    .get_mut(
+                        *children
+                            .into_iter()
+                            .find(|&&child| blocks_texts.contains(child))
+                            .unwrap(),
+                    )
+                    .unwrap();
                 text.sections[0].value = lives.0.to_string();
 
                 if lives.0 == 0 {
-                    commands.entity(blocks_parent.single()).remove_children(&[hit_entity]);
+                    commands
+                        .entity(blocks_parent.single())
+                        .remove_children(&[hit_entity]);
                     commands.entity(hit_entity).despawn_recursive();
-                    simulation.state.blocks.remove(index);
-                    simulation.balls_increment += 1;
-                    block_ids.0.remove(index);
+                    block_ids.0.remove(block);
                 }
             }
+            TurnEvent::Collected { pickup, .. } => {
+                let collected = pickup_ids.0.remove(pickup);
+                commands
+                    .entity(blocks_parent.single())
+                    .remove_children(&[collected]);
+                commands.entity(collected).despawn();
+            }
+            TurnEvent::Other => {}
         }
 
-        interpolated_simulation.state = simulation.state.clone();
+        let Simulation {
+            turn, interpolator, ..
+        } = &mut *simulation;
+        interpolator.push(&turn.state, &event);
     }
 }
 
 fn interpolate_simulation(
     time: Query<&SimulationWatch>,
     simulation: Query<&Simulation>,
-    mut interpolated_simulation: Query<&mut InterpolatedSimulation>,
+    ball_ids: Query<&BallEntities>,
+    mut balls: Query<(&mut Transform, &mut Visibility), With<Ball>>,
 ) {
-    let mut interpolated_simulation = interpolated_simulation.single_mut();
-    let simulation = simulation.single();
-    let time = time.single();
-    let advance = time.0.elapsed_secs_f64() - interpolated_simulation.state.time;
-    for (mut ball, initial_ball) in interpolated_simulation
-        .state
-        .balls
-        .iter_mut()
-        .zip(&simulation.state.balls)
-    {
-        ball.position = initial_ball.position + initial_ball.velocity * advance;
+    let time = time.single().0.elapsed_secs_f64();
+    let interpolator = &simulation.single().interpolator;
+    let ball_ids = ball_ids.single();
+    for (track, position) in interpolator.positions_at(time) {
+        let radius = interpolator.tracks()[track].radius as f32;
+        // Entities of balls launched this frame aren't spawned yet
+        if let Ok((mut transform, mut visibility)) = balls.get_mut(ball_ids.0[track]) {
+            transform.translation = Vec3::new(position.x as f32, position.y as f32, -0.5);
+            transform.scale = Vec2::new(radius, radius).extend(1.0);
+            *visibility = Visibility::Visible;
+        }
     }
 }
 
-fn handle_stop(
-    keys: Res<Input<KeyCode>>,
+fn draw_trails(
+    time: Query<&SimulationWatch>,
     simulation: Query<&Simulation>,
-    mut inner_game_state: ResMut<NextState<InnerGameState>>,
+    settings: Res<TrailSettings>,
+    mut gizmos: Gizmos,
 ) {
-    if simulation.single().next.is_none() || keys.just_pressed(KeyCode::Space) {
-        inner_game_state.set(InnerGameState::AnimateBlocksIn);
+    let time = time.single().0.elapsed_secs_f64();
+    let step = settings.length / TRAIL_SEGMENTS as f64;
+    let alpha = settings.color.a();
+    let color_at = |point_time: f64| {
+        let fade = 1.0 - (time - point_time) / settings.length;
+        settings.color.with_a(alpha * fade.clamp(0.0, 1.0) as f32)
+    };
+
+    // Removed balls keep their trail until it fades out
+    for track in simulation.single().interpolator.tracks() {
+        let path = track.path(time - settings.length, time);
+        for window in path.windows(2) {
+            let [(start_time, start), (end_time, end)] = [window[0], window[1]];
+            let pieces = ((end_time - start_time) / step).ceil().max(1.0);
+            for piece in 0..pieces as usize {
+                let from = piece as f64 / pieces;
+                let to = (piece + 1) as f64 / pieces;
+                let a = start.lerp(&end, from);
+                let b = start.lerp(&end, to);
+                gizmos.line_2d(
+                    Vec2::new(a.x as f32, a.y as f32),
+                    Vec2::new(b.x as f32, b.y as f32),
+                    color_at(start_time + (end_time - start_time) * to),
+                );
+            }
+        }
     }
 }
 
-fn update_balls(
-    simulation: Query<&InterpolatedSimulation>,
-    mut balls: Query<(&mut Transform, &mut Visibility), With<Ball>>,
+fn handle_stop(
+    keys: Res<Input<KeyCode>>,
+    mut simulation: Query<&mut Simulation>,
+    mut inner_game_state: ResMut<NextState<InnerGameState>>,
 ) {
-    let simulation = simulation.single();
-    for ((mut ball_entity, mut visibility), ball) in balls.iter_mut().zip(&simulation.state.balls) {
-        ball_entity.translation = Vec3::new(ball.position.x as f32, ball.position.y as f32, -0.5);
-        ball_entity.scale = Vec2::new(ball.radius as f32, ball.radius as f32).extend(1.0);
-        *visibility = Visibility::Visible;
+    if simulation.single_mut().turn.is_finished() || keys.just_pressed(KeyCode::Space) {
+        inner_game_state.set(InnerGameState::AnimateBlocksIn);
     }
 }
 
-fn save_state(
-    blocks: Query<(&GridPosition, &Lives), With<Block>>,
-    mut board_state: Query<&mut BoardState>,
-    simulation: Query<&Simulation>,
-) {
-    let simulation = simulation.single();
-    let board_state = &mut board_state.single_mut();
-
-    for lives in board_state.blocks.iter_mut() {
-        *lives = 0;
-    }
+/// Ends the shot where `simulation` is. Stopping a shot keeps what it did so
+/// far, balls still in flight are lost.
+pub(super) fn stop_shot(board_state: &mut BoardState, simulation: &Simulation) {
+    board_state.board.resolve_shot(simulation.turn.clone());
+    board_state.phase = Phase::NewRow;
+}
 
-    board_state.ball_count += simulation.balls_increment;
-    for (block_position, lives) in blocks.iter() {
-        board_state.blocks[(block_position.0.y, block_position.0.x)] = lives.0;
-    }
+fn save_state(mut board_state: Query<&mut BoardState>, simulation: Query<&Simulation>) {
+    stop_shot(&mut board_state.single_mut(), simulation.single());
 }
@@ -1,3 +1,5 @@
+use ball_game_core::Cell;
+use ball_simulation::Sensor;
 use bevy::{
     math::{vec2, vec3},
     prelude::*,
@@ -13,17 +15,6 @@ pub(crate) fn get_block_separations(columns: usize, rows: usize) -> Vector2<f64>
     Vector2::new(x, y)
 }
 
-pub(crate) fn get_block(columns: usize, rows: usize, x: usize, y: usize) -> ball_simulation::Block {
-    let y = (1.0 + y as f64) / (rows as f64 + 1.0);
-    let x = (1.0 + x as f64) / (columns as f64 + 1.0);
-    ball_simulation::Block {
-        min_y: y - 0.04,
-        max_y: y + 0.04,
-        min_x: x - 0.04,
-        max_x: x + 0.04,
-    }
-}
-
 #[derive(Component)]
 pub(crate) struct Lives(pub usize);
 
@@ -33,6 +24,9 @@ pub(crate) struct Ball;
 #[derive(Component)]
 pub(crate) struct Block;
 
+#[derive(Component)]
+pub(crate) struct Pickup;
+
 fn add_block(
     commands: &mut Commands,
     block_ids: &mut Vec<Entity>,
@@ -113,6 +107,44 @@ pub(crate) fn add_blocks_from_state(
         .collect()
 }
 
+/// Draws each pickup as a dot filling its sensor, coloured by what it gives.
+pub(crate) fn add_pickups_from_state(
+    pickups: &[(Sensor, Cell)],
+    pickup_ids: &mut Vec<Entity>,
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    parent: Entity,
+) {
+    let pickup_mesh: Mesh2dHandle = meshes.add(shape::Circle::new(1.0).into()).into();
+
+    for &(sensor, cell) in pickups {
+        let color = match cell {
+            Cell::Coin => *colors::COINS,
+            _ => *colors::BALLS,
+        };
+        let radius = (sensor.max_x - sensor.min_x) as f32 / 2.0;
+        let id = commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: pickup_mesh.clone(),
+                    material: materials.add(ColorMaterial::from(color)),
+                    transform: Transform::from_xyz(
+                        (sensor.min_x + sensor.max_x) as f32 / 2.0,
+                        (sensor.min_y + sensor.max_y) as f32 / 2.0,
+                        -0.5,
+                    )
+                    .with_scale(Vec3::new(radius, radius, 1.0)),
+                    ..default()
+                },
+                Pickup,
+            ))
+            .id();
+        commands.entity(parent).push_children(&[id]);
+        pickup_ids.push(id);
+    }
+}
+
 pub(crate) fn add_ball<State: Component>(
     commands: &mut Commands,
     ball_ids: &mut Vec<Entity>,
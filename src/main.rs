@@ -4,6 +4,7 @@
 mod colors;
 mod game;
 mod menu;
+mod storage;
 
 use bevy::prelude::*;
 
@@ -1,6 +1,11 @@
+use ball_game_core::{daily_seed, HighScores};
 use bevy::{app::AppExit, prelude::*};
 
-use crate::{colors, despawn_screen, GameState};
+use crate::{
+    colors, despawn_screen,
+    game::{has_saved_game, GameStart},
+    storage, GameState,
+};
 
 #[derive(Component)]
 pub struct OnMenu;
@@ -9,21 +14,69 @@ pub struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Menu), menu_setup)
-            .add_systems(Update, menu_action.run_if(in_state(GameState::Menu)))
+        app.init_resource::<SeedInput>()
+            .add_systems(OnEnter(GameState::Menu), menu_setup)
+            .add_systems(
+                Update,
+                (menu_action, seed_input).run_if(in_state(GameState::Menu)),
+            )
             .add_systems(OnExit(GameState::Menu), despawn_screen::<OnMenu>);
     }
 }
 
 #[derive(Component)]
 enum MenuButtonAction {
+    Continue,
     Play,
+    Daily,
     Quit,
 }
 
-fn menu_setup(mut commands: Commands, assets: Res<AssetServer>) {
+/// Seed typed in the menu, Play uses a random one while it is empty.
+#[derive(Resource, Default)]
+struct SeedInput(String);
+
+impl SeedInput {
+    /// Enough digits for any `u64`.
+    const MAX_LENGTH: usize = 19;
+
+    fn label(&self) -> String {
+        if self.0.is_empty() {
+            "Seed: random (type digits to choose)".to_string()
+        } else {
+            format!("Seed: {}", self.0)
+        }
+    }
+}
+
+#[derive(Component)]
+struct SeedText;
+
+/// Today's seed, the same for everyone as the day is counted in UTC.
+fn today_seed() -> u64 {
+    daily_seed(unix_seconds() / 86_400)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_seconds() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default()
+}
+
+// `SystemTime::now` panics in the browser, ask the page's clock instead
+#[cfg(target_arch = "wasm32")]
+fn unix_seconds() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+fn menu_setup(mut commands: Commands, assets: Res<AssetServer>, seed_input: Res<SeedInput>) {
     commands.spawn((Camera2dBundle::default(), OnMenu));
     let font = assets.load::<Font, _>("fonts/OpenSans-Regular.ttf");
+    let high_scores: HighScores = storage::load(storage::HIGH_SCORES).unwrap_or_default();
 
     let button_text_style = TextStyle {
         font_size: 40.0,
@@ -61,7 +114,7 @@ fn menu_setup(mut commands: Commands, assets: Res<AssetServer>) {
                     TextStyle {
                         font_size: 60.0,
                         color: *colors::DARK_TEXT,
-                        font,
+                        font: font.clone(),
                     },
                 )
                 .with_style(Style {
@@ -69,6 +122,60 @@ fn menu_setup(mut commands: Commands, assets: Res<AssetServer>) {
                     ..default()
                 }),
             );
+            if !high_scores.entries().is_empty() {
+                let table = high_scores
+                    .entries()
+                    .iter()
+                    .enumerate()
+                    .map(|(rank, score)| {
+                        format!("{}. {} (turn {})", rank + 1, score.points, score.round)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                parent.spawn(TextBundle::from_section(
+                    format!("High scores\n{table}"),
+                    TextStyle {
+                        font_size: 30.0,
+                        color: *colors::DARK_TEXT,
+                        font: font.clone(),
+                    },
+                ));
+            }
+            parent.spawn((
+                TextBundle::from_section(
+                    seed_input.label(),
+                    TextStyle {
+                        font_size: 30.0,
+                        color: *colors::DARK_TEXT,
+                        font,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                }),
+                SeedText,
+            ));
+            if has_saved_game() {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(180.0), Val::Px(50.0)),
+                                ..button_style.clone()
+                            },
+                            background_color: (*colors::BUTTON_BACKGROUND).into(),
+                            ..default()
+                        },
+                        MenuButtonAction::Continue,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            "Continue",
+                            button_text_style.clone(),
+                        ));
+                    });
+            }
             parent
                 .spawn((
                     ButtonBundle {
@@ -81,6 +188,18 @@ fn menu_setup(mut commands: Commands, assets: Res<AssetServer>) {
                 .with_children(|parent| {
                     parent.spawn(TextBundle::from_section("Play", button_text_style.clone()));
                 });
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: button_style.clone(),
+                        background_color: (*colors::BUTTON_BACKGROUND).into(),
+                        ..default()
+                    },
+                    MenuButtonAction::Daily,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Daily", button_text_style.clone()));
+                });
             parent
                 .spawn((
                     ButtonBundle {
@@ -103,15 +222,52 @@ fn menu_action(
     >,
     mut app_exit_events: EventWriter<AppExit>,
     mut game_state: ResMut<NextState<GameState>>,
+    mut game_start: ResMut<GameStart>,
+    seed_input: Res<SeedInput>,
 ) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Clicked {
             match menu_button_action {
                 MenuButtonAction::Quit => app_exit_events.send(AppExit),
+                MenuButtonAction::Continue => {
+                    *game_start = GameStart::Continue;
+                    game_state.set(GameState::Game);
+                }
                 MenuButtonAction::Play => {
+                    *game_start = match seed_input.0.parse() {
+                        Ok(seed) => GameStart::Seeded(seed),
+                        Err(_) => GameStart::New,
+                    };
+                    game_state.set(GameState::Game);
+                }
+                MenuButtonAction::Daily => {
+                    *game_start = GameStart::Seeded(today_seed());
                     game_state.set(GameState::Game);
                 }
             }
         }
     }
 }
+
+fn seed_input(
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut seed_input: ResMut<SeedInput>,
+    mut seed_text: Query<&mut Text, With<SeedText>>,
+) {
+    let mut changed = false;
+    for character in characters.iter() {
+        if character.char.is_ascii_digit() && seed_input.0.len() < SeedInput::MAX_LENGTH {
+            seed_input.0.push(character.char);
+            changed = true;
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        changed |= seed_input.0.pop().is_some();
+    }
+    if changed {
+        for mut text in &mut seed_text {
+            text.sections[0].value = seed_input.label();
+        }
+    }
+}
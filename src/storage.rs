@@ -0,0 +1,101 @@
+use bevy::prelude::warn;
+use serde::{de::DeserializeOwned, Serialize};
+
+pub(crate) const HIGH_SCORES: &str = "high_scores.ron";
+pub(crate) const SAVED_GAME: &str = "saved_game.ron";
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::{fs, path::PathBuf};
+
+    fn path(name: &str) -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("ball_game").join(name))
+    }
+
+    pub(super) fn read(name: &str) -> Option<String> {
+        fs::read_to_string(path(name)?).ok()
+    }
+
+    pub(super) fn write(name: &str, content: &str) -> Result<(), String> {
+        let path = path(name).ok_or("no data directory")?;
+        fs::create_dir_all(path.parent().unwrap()).map_err(|error| error.to_string())?;
+        fs::write(&path, content).map_err(|error| error.to_string())
+    }
+
+    pub(super) fn exists(name: &str) -> bool {
+        path(name).is_some_and(|path| path.exists())
+    }
+
+    pub(super) fn remove(name: &str) -> Result<(), String> {
+        match path(name) {
+            Some(path) if path.exists() => fs::remove_file(path).map_err(|error| error.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Browsers have no file system to write to, so the web build keeps its files
+// in the page's local storage
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use web_sys::Storage;
+
+    fn storage() -> Option<Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    fn key(name: &str) -> String {
+        format!("ball_game/{name}")
+    }
+
+    pub(super) fn read(name: &str) -> Option<String> {
+        storage()?.get_item(&key(name)).ok()?
+    }
+
+    pub(super) fn write(name: &str, content: &str) -> Result<(), String> {
+        storage()
+            .ok_or("no local storage")?
+            .set_item(&key(name), content)
+            .map_err(|error| format!("{error:?}"))
+    }
+
+    pub(super) fn exists(name: &str) -> bool {
+        read(name).is_some()
+    }
+
+    pub(super) fn remove(name: &str) -> Result<(), String> {
+        match storage() {
+            Some(storage) => storage
+                .remove_item(&key(name))
+                .map_err(|error| format!("{error:?}")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reads a file saved with [`save`], `None` when it is missing or unreadable.
+pub(crate) fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let content = backend::read(name)?;
+    ron::from_str(&content)
+        .map_err(|error| warn!("Ignoring {name}: {error}"))
+        .ok()
+}
+
+pub(crate) fn exists(name: &str) -> bool {
+    backend::exists(name)
+}
+
+pub(crate) fn remove(name: &str) {
+    if let Err(error) = backend::remove(name) {
+        warn!("Could not remove {name}: {error}");
+    }
+}
+
+pub(crate) fn save<T: Serialize>(name: &str, value: &T) {
+    let result = ron::to_string(value)
+        .map_err(|error| error.to_string())
+        .and_then(|content| backend::write(name, &content));
+    if let Err(error) = result {
+        warn!("Could not save {name}: {error}");
+    }
+}